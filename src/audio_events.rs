@@ -1,107 +1,334 @@
+use crate::events::EventKind;
+use crate::game_events::{GameEvent, GameEventSubscriber};
+use crate::helpers::Rng;
+use alloc::string::String;
 use alloc::vec::Vec;
-use anyhow::Error;
+use anyhow::{anyhow, Error};
+use crankstart::file::FileSystem;
 use crankstart::log_to_console;
 use crankstart::sound::{AudioSample, SamplePlayer, Sound};
+use crankstart::system::System;
+use crankstart_sys::FileOptions;
 use hashbrown::HashMap;
+use serde::Deserialize;
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+/// Lists the samples for each event, bundled with the game
+const SOUND_BANK_FILENAME: &str = "res/audio/sound_bank.json";
+const SOUND_BANK_BUFFER_SIZE: usize = 2048;
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Deserialize)]
 pub enum AudioEvent {
     MoneyGained,
     UpgradeBought,
     UpgradeDenied,
     DoughCreated,
+    GoldenDoughAppeared,
+    RushOrderArrived,
+    FlourShortageStarted,
 }
 
 impl AudioEvent {
-    const ALL: [AudioEvent; 4] = [
+    const ALL: [AudioEvent; 7] = [
         AudioEvent::MoneyGained,
         AudioEvent::UpgradeBought,
         AudioEvent::UpgradeDenied,
         AudioEvent::DoughCreated,
+        AudioEvent::GoldenDoughAppeared,
+        AudioEvent::RushOrderArrived,
+        AudioEvent::FlourShortageStarted,
     ];
 
-    fn to_sound_file(&self) -> &'static str {
-        match self {
-            AudioEvent::MoneyGained => "res/audio/75235__creek23__cha-ching.wav",
-            AudioEvent::UpgradeBought => "res/audio/611800__metalfortress__confirm.wav",
-            AudioEvent::UpgradeDenied => "res/audio/220187__gameaudio__loosedeny-casual-1.wav",
-            AudioEvent::DoughCreated => {
-                "res/audio/330997__rudmer_rotteveel__stick-hitting-a-dreadlock-small-thud.wav"
+    /// The sound for a gameplay event, if it has one
+    fn for_game_event(event: &GameEvent) -> Option<AudioEvent> {
+        match event {
+            GameEvent::MoneyGained { .. } => Some(AudioEvent::MoneyGained),
+            GameEvent::UpgradeBought { .. } | GameEvent::GoldenDoughTaken => {
+                Some(AudioEvent::UpgradeBought)
             }
+            GameEvent::UpgradeDenied { .. } | GameEvent::SaleDenied => {
+                Some(AudioEvent::UpgradeDenied)
+            }
+            GameEvent::DoughCreated => Some(AudioEvent::DoughCreated),
+            GameEvent::RandomEventStarted(kind) => Some(match kind {
+                EventKind::GoldenDough => AudioEvent::GoldenDoughAppeared,
+                EventKind::RushOrder => AudioEvent::RushOrderArrived,
+                EventKind::FlourShortage => AudioEvent::FlourShortageStarted,
+            }),
+            GameEvent::Kneaded
+            | GameEvent::SheetMade { .. }
+            | GameEvent::ShopOpened
+            | GameEvent::Cranked { .. } => None,
+        }
+    }
+
+    fn mix(&self) -> EventMix {
+        match self {
+            AudioEvent::MoneyGained => EventMix {
+                pitch_variation: 0.08,
+                max_voices: 2,
+                cooldown: 0.15,
+                priority: 1,
+            },
+            AudioEvent::UpgradeBought | AudioEvent::UpgradeDenied => EventMix {
+                pitch_variation: 0.0,
+                max_voices: 1,
+                cooldown: 0.0,
+                priority: 3,
+            },
+            AudioEvent::DoughCreated => EventMix {
+                pitch_variation: 0.15,
+                max_voices: 2,
+                cooldown: 0.1,
+                priority: 0,
+            },
+            AudioEvent::GoldenDoughAppeared
+            | AudioEvent::RushOrderArrived
+            | AudioEvent::FlourShortageStarted => EventMix {
+                pitch_variation: 0.0,
+                max_voices: 1,
+                cooldown: 1.0,
+                priority: 4,
+            },
         }
     }
-    fn load_sound(&self) -> Result<AudioSample, Error> {
+}
+
+/// How an [AudioEvent] is mixed in with everything else that's playing
+#[derive(Debug, Copy, Clone)]
+struct EventMix {
+    /// Each play picks a playback rate within this much of normal speed, so repeats don't sound
+    /// identical
+    pitch_variation: f32,
+    /// Most copies of this event that can play at once, further ones are dropped
+    max_voices: usize,
+    /// Seconds after playing this event during which it won't be played again
+    cooldown: f32,
+    /// When all voices are busy, a new sound can steal the voice of a lower priority one
+    priority: u8,
+}
+
+/// One event's entry in the sound bank manifest
+#[derive(Debug, Deserialize)]
+struct SoundBankEntry {
+    event: AudioEvent,
+    /// Sample paths, one is picked at random each time the event plays
+    variants: Vec<String>,
+    /// Relative to the other events, before the settings volume is applied
+    #[serde(default = "SoundBankEntry::default_volume")]
+    volume: f32,
+}
+
+impl SoundBankEntry {
+    fn default_volume() -> f32 {
+        1.0
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SoundBankManifest {
+    sounds: Vec<SoundBankEntry>,
+}
+
+impl SoundBankManifest {
+    fn load() -> Result<Self, Error> {
+        let mut fs = FileSystem::get();
+        let mut file = fs.open(SOUND_BANK_FILENAME, FileOptions::kFileRead)?;
+        let mut buf = [0u8; SOUND_BANK_BUFFER_SIZE];
+        let bytes_read = file.read(&mut buf)?;
+        let (manifest, _): (SoundBankManifest, usize) =
+            serde_json_core::de::from_slice(&buf[..bytes_read])
+                .map_err(|e| anyhow!("Serde-error deserialising: {}", e))?;
+        Ok(manifest)
+    }
+}
+
+/// The loaded samples for one event
+#[derive(Debug)]
+struct EventSounds {
+    variants: Vec<AudioSample>,
+    volume: f32,
+}
+
+impl EventSounds {
+    /// Loads what it can of the entry's samples, leaving out any that fail
+    fn load(entry: &SoundBankEntry) -> Self {
         let sound = Sound::get();
-        sound.load_audio_sample(self.to_sound_file()).map_err(|e| {
-            log_to_console!("Failed to load audio sample: {:?}", e);
-            e
-        })
+        let variants = entry
+            .variants
+            .iter()
+            .filter_map(|path| match sound.load_audio_sample(path) {
+                Ok(sample) => Some(sample),
+                Err(e) => {
+                    log_to_console!("Failed to load audio sample {}: {:?}", path, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            variants,
+            volume: entry.volume,
+        }
     }
 }
 
+#[derive(Debug)]
+struct Voice {
+    event: AudioEvent,
+    priority: u8,
+    player: SamplePlayer,
+}
+
 #[derive(Debug)]
 pub struct SoundStore {
-    sounds: HashMap<AudioEvent, AudioSample>,
-    players: Vec<SamplePlayer>,
+    /// Events missing from here, or without any samples that loaded, are silent
+    sounds: HashMap<AudioEvent, EventSounds>,
+    voices: Vec<Voice>,
+    /// Elapsed time each event was last played at
+    last_played: HashMap<AudioEvent, f32>,
+    rng: Rng,
+    /// From 0.0 to 1.0, set from the settings
+    volume: f32,
 }
 
 impl SoundStore {
-    pub fn new() -> Result<Self, Error> {
-        {
-            let mut sound = Sound::get();
-            sound.set_outputs_active(true, true)?;
+    /// Most sounds playing at once across all events
+    const MAX_VOICES: usize = 6;
+
+    /// Never fails, a sound that can't be loaded is logged and left silent rather than stopping the
+    /// game
+    pub fn new() -> Self {
+        if let Err(e) = Sound::get().set_outputs_active(true, true) {
+            log_to_console!("Failed to activate sound outputs: {:?}", e);
         }
-        let sounds = AudioEvent::ALL
+        let manifest = SoundBankManifest::load().unwrap_or_else(|e| {
+            log_to_console!(
+                "Failed to load sound bank, all sounds are silent. Error: {:?}",
+                e
+            );
+            SoundBankManifest { sounds: Vec::new() }
+        });
+        let sounds: HashMap<AudioEvent, EventSounds> = manifest
+            .sounds
             .iter()
-            .map(|event| event.load_sound().map(|sample| (*event, sample)))
-            .collect::<Result<HashMap<AudioEvent, AudioSample>, _>>()?;
-        Ok(Self {
+            .map(|entry| (entry.event, EventSounds::load(entry)))
+            .collect();
+        let store = Self {
             sounds,
-            players: Vec::new(),
-        })
+            voices: Vec::new(),
+            last_played: HashMap::new(),
+            rng: Rng::from_time(),
+            volume: 1.0,
+        };
+        for event in AudioEvent::ALL.iter() {
+            if !store.has_sound(event) {
+                log_to_console!("No sound for {:?}, it will be silent", event);
+            }
+        }
+        store
     }
 
-    fn get_sample(&self, event: &AudioEvent) -> Option<&AudioSample> {
-        self.sounds.get(event)
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
     }
-}
 
-#[derive(Debug)]
-pub struct AudioEventChannel(Vec<AudioEvent>);
+    /// True while any sound effect is still playing
+    pub fn is_playing(&self) -> bool {
+        !self.voices.is_empty()
+    }
 
-impl AudioEventChannel {
-    pub fn new() -> Self {
-        Self(Vec::new())
+    fn has_sound(&self, event: &AudioEvent) -> bool {
+        self.sounds
+            .get(event)
+            .is_some_and(|sounds| !sounds.variants.is_empty())
     }
 
-    pub fn push(&mut self, event: AudioEvent) {
-        self.0.push(event);
+    /// Picks one of the event's variants at random, along with the event's volume
+    fn pick_sample(&mut self, event: &AudioEvent) -> Option<(&AudioSample, f32)> {
+        let sounds = self.sounds.get(event)?;
+        if sounds.variants.is_empty() {
+            return None;
+        }
+        let idx = self.rng.range_usize(0, sounds.variants.len() - 1);
+        Some((&sounds.variants[idx], sounds.volume))
+    }
+
+    fn on_cooldown(&self, event: AudioEvent, mix: &EventMix, now: f32) -> bool {
+        match self.last_played.get(&event) {
+            Some(last_played) => now - last_played < mix.cooldown,
+            None => false,
+        }
     }
-}
 
-fn play_sample(
-    sound: &Sound,
-    event: &AudioEvent,
-    samples: &HashMap<AudioEvent, AudioSample>,
-) -> Result<SamplePlayer, Error> {
-    log_to_console!("Playing sample: {:?}", event);
-    let sample = samples
-        .get(event)
-        .ok_or(anyhow::anyhow!("No sample for event"))?;
-    let mut player = sound.get_sample_player()?;
-    player.set_sample(&sample)?;
-    player.play(1, 1.0)?;
-    Ok(player)
+    /// Makes room for a new sound, returning false if there isn't any to be had
+    fn claim_voice(&mut self, event: AudioEvent, mix: &EventMix) -> bool {
+        let event_voices = self.voices.iter().filter(|v| v.event == event).count();
+        if event_voices >= mix.max_voices {
+            return false;
+        }
+        if self.voices.len() < Self::MAX_VOICES {
+            return true;
+        }
+        // Voices are in the order they started, so this steals the oldest of the lowest priority
+        let steal_idx = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter(|(_, voice)| voice.priority < mix.priority)
+            .min_by_key(|(_, voice)| voice.priority)
+            .map(|(idx, _)| idx);
+        match steal_idx {
+            Some(idx) => {
+                let stolen = self.voices.remove(idx);
+                stolen.player.stop().ok();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn play(&mut self, event: AudioEvent, now: f32) -> Result<(), Error> {
+        let mix = event.mix();
+        if !self.has_sound(&event)
+            || self.on_cooldown(event, &mix, now)
+            || !self.claim_voice(event, &mix)
+        {
+            return Ok(());
+        }
+        let rate = 1.0
+            + self
+                .rng
+                .range_f32(-mix.pitch_variation, mix.pitch_variation);
+        let settings_volume = self.volume;
+        let (sample, event_volume) = self
+            .pick_sample(&event)
+            .ok_or(anyhow!("No sample for event"))?;
+        let volume = event_volume * settings_volume;
+        let mut player = Sound::get().get_sample_player()?;
+        player.set_sample(sample)?;
+        player.set_volume(volume, volume)?;
+        player.play(1, rate)?;
+        self.last_played.insert(event, now);
+        self.voices.push(Voice {
+            event,
+            priority: mix.priority,
+            player,
+        });
+        Ok(())
+    }
 }
-pub fn process_events(channel: &mut AudioEventChannel, sound_store: &mut SoundStore) {
-    for event in channel.0.drain(..) {
-        let sound = Sound::get();
-        match play_sample(&sound, &event, &sound_store.sounds) {
-            Ok(player) => sound_store.players.push(player),
-            Err(e) => log_to_console!("Failed to play sample: {:?}", e),
+
+impl GameEventSubscriber for SoundStore {
+    fn on_events(&mut self, events: &[GameEvent]) {
+        self.voices
+            .retain(|voice| voice.player.is_playing().unwrap_or(false));
+        if self.volume <= 0.0 {
+            return;
+        }
+        let now = System::get().get_elapsed_time().unwrap_or(0.0);
+        for event in events.iter().filter_map(AudioEvent::for_game_event) {
+            if let Err(e) = self.play(event, now) {
+                log_to_console!("Failed to play sample: {:?}", e);
+            }
         }
     }
-    sound_store
-        .players
-        .retain(|player| player.is_playing().unwrap_or(false));
 }
@@ -1,20 +1,27 @@
 use crate::core_elements::{TextSpriteWithValue, VisibilityState};
+use crate::game_events::{GameEvent, GameEventBus};
 use crate::game_value::{GameUInt, GameValue};
-use crate::helpers::load_sprite_at;
-use crate::{CoreState, Menu, SpriteType};
+use crate::orders::OrderBoard;
+use crate::sprite_owner::SpriteOwner;
+use crate::sprite_registry::SpriteRegistry;
+use crate::tween::RollingCounter;
+use crate::{CoreState, GameState, Menu};
 use alloc::boxed::Box;
 use alloc::format;
 use core::ops::Not;
 use crankstart::graphics::{Bitmap, Graphics, LCDColor};
-use crankstart::sprite::{Sprite, SpriteManager, TextSprite};
+use crankstart::sprite::Sprite;
 use crankstart::system;
 use crankstart::system::System;
-use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons};
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons, PDRect};
 
 #[derive(Debug)]
 pub struct BottomBar {
+    _sprites: SpriteOwner,
     background: Sprite,
     money: TextSpriteWithValue<GameUInt>,
+    /// The money shown rolls up to the real amount
+    money_roll: RollingCounter,
     diamonds: TextSpriteWithValue<GameUInt>,
     menu_indicator: MenuIndicator,
     money_icon: Sprite,
@@ -25,18 +32,12 @@ impl BottomBar {
     pub fn new() -> Self {
         let z = 20;
         let y = 216.0;
-        let mut background = {
-            crate::helpers::load_sprite_at(
-                "res/bottom_bar",
-                200.0,
-                y,
-                Some(SpriteType::BottomBar as u8),
-            )
-        };
+        let mut sprites = SpriteOwner::new();
+        let mut background = sprites.load_sprite_at("res/bottom_bar", 200.0, y);
         background.set_z_index(z);
-        let mut money_icon = load_sprite_at("res/coin", 290.0, y, None);
+        let mut money_icon = sprites.load_sprite_at("res/coin", 290.0, y);
         money_icon.set_z_index(z + 1).unwrap();
-        let mut money = TextSprite::new("", LCDColor::Solid(LCDSolidColor::kColorWhite)).unwrap();
+        let mut money = sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorWhite));
         money.get_sprite_mut().move_to(335.0, y).unwrap();
         money.get_sprite_mut().set_z_index(z + 1).unwrap();
         let money = TextSpriteWithValue::new(
@@ -44,10 +45,9 @@ impl BottomBar {
             GameUInt::default(),
             Box::new(GameUInt::to_string_hum),
         );
-        let mut diamond_icon = load_sprite_at("res/diamond", 105.0, y, None);
+        let mut diamond_icon = sprites.load_sprite_at("res/diamond", 105.0, y);
         diamond_icon.set_z_index(z + 1).unwrap();
-        let mut diamonds =
-            TextSprite::new("", LCDColor::Solid(LCDSolidColor::kColorWhite)).unwrap();
+        let mut diamonds = sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorWhite));
         diamonds.get_sprite_mut().move_to(135.0, y).unwrap();
         diamonds.get_sprite_mut().set_z_index(z + 1).unwrap();
         let diamonds = TextSpriteWithValue::new(
@@ -55,10 +55,12 @@ impl BottomBar {
             GameUInt::default(),
             Box::new(GameUInt::to_string_hum),
         );
-        let menu_indicator = MenuIndicator::new(30.0, y);
+        let menu_indicator = MenuIndicator::new(30.0, y, &mut sprites);
         Self {
+            _sprites: sprites,
             background,
             money,
+            money_roll: RollingCounter::new(GameUInt::default(), 0.6),
             diamonds,
             menu_indicator,
             money_icon,
@@ -66,14 +68,49 @@ impl BottomBar {
         }
     }
 
-    pub fn update(&mut self, state: &CoreState, menu: &mut Menu) {
-        self.money.update_value(&state.money);
+    pub fn menu_indicator_bounds(&self) -> PDRect {
+        self.menu_indicator.sprite.get_bounds().unwrap()
+    }
+
+    pub fn register_sprites(&mut self, registry: &mut SpriteRegistry<GameState>) {
+        registry.on_update(&mut self.background, |game| {
+            game.bottom_bar.update(
+                &game.state,
+                &mut game.menu,
+                &mut game.order_board,
+                &mut game.game_events,
+            )
+        });
+    }
+
+    pub fn update(
+        &mut self,
+        state: &CoreState,
+        menu: &mut Menu,
+        order_board: &mut OrderBoard,
+        game_events: &mut GameEventBus,
+    ) {
+        self.money_roll.set_target(&state.money);
+        self.money.update_value(&self.money_roll.value());
         self.diamonds.update_value(&state.diamonds);
 
         let (_, pressed, _released) = System::get().get_button_state().unwrap();
-        if (pressed & self.menu_indicator.get_toggle_button()).0 != 0 {
+        let left_pressed = (pressed & PDButtons::kButtonLeft).0 != 0;
+        let right_pressed = (pressed & PDButtons::kButtonRight).0 != 0;
+        // The shop comes in from the left and the order board from the right, so the opposite
+        // direction closes whichever is open
+        if order_board.is_visible() {
+            if left_pressed {
+                order_board.set_state(VisibilityState::Hidden);
+            }
+        } else if right_pressed && matches!(self.menu_indicator.state, VisibilityState::Hidden) {
+            order_board.open(state);
+        } else if (pressed & self.menu_indicator.get_toggle_button()).0 != 0 {
             self.menu_indicator.toggle();
             menu.set_state(self.menu_indicator.state);
+            if matches!(self.menu_indicator.state, VisibilityState::Visible) {
+                game_events.push(GameEvent::ShopOpened);
+            }
         }
     }
 }
@@ -87,7 +124,7 @@ struct MenuIndicator {
 }
 
 impl MenuIndicator {
-    fn new(x: f32, y: f32) -> Self {
+    fn new(x: f32, y: f32, sprites: &mut SpriteOwner) -> Self {
         let hidden_image = Graphics::get()
             .load_bitmap("res/menu_indicator_left")
             .unwrap();
@@ -95,14 +132,12 @@ impl MenuIndicator {
             .load_bitmap("res/menu_indicator_right")
             .unwrap();
         let sprite = {
-            let sprite_manager = SpriteManager::get_mut();
-            let mut sprite = sprite_manager.new_sprite().unwrap();
+            let mut sprite = sprites.new_sprite();
             sprite
                 .set_image(hidden_image.clone(), LCDBitmapFlip::kBitmapUnflipped)
                 .unwrap();
             sprite.move_to(x, y).unwrap();
             sprite.set_z_index(21).unwrap();
-            sprite_manager.add_sprite(&sprite).unwrap();
             sprite
         };
         Self {
@@ -1,5 +1,7 @@
-use crate::game_value::GameUInt;
-use crate::SpriteType;
+use crate::game_value::{GameDecimal, GameUInt, NumberFormat};
+use crate::machine::MachineUpgrades;
+use crate::production::DryingRack;
+use crate::sprite_owner::SpriteOwner;
 use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::String;
@@ -7,7 +9,7 @@ use alloc::vec::Vec;
 use core::fmt::{Debug, Formatter};
 use core::ops::{Add, Not};
 use crankstart::graphics::{Bitmap, Graphics, LCDColor};
-use crankstart::sprite::{Sprite, SpriteManager, TextSprite};
+use crankstart::sprite::{Sprite, TextSprite};
 use crankstart::system;
 use crankstart::system::System;
 use crankstart_sys::{LCDBitmapFlip, LCDSolidColor};
@@ -20,20 +22,131 @@ pub struct CoreParameters {
     /// How much each knead tick increases the fill bar
     pub(crate) knead_tick_size: f32,
     /// How much each pasta is worth
-    pub(crate) pasta_price: GameUInt,
+    pub(crate) pasta_price: GameDecimal,
     /// How much autocranking occurs
     pub(crate) auto_crank_level: usize,
-    /// How much autokneading occurs
+    /// How much autokneading occurs, i.e. how many grans have been hired
     pub(crate) auto_knead_level: usize,
+    /// How many Nonnas have been hired, they knead faster than grans
+    #[serde(default)]
+    pub(crate) nonna_level: usize,
+    /// How many Apprentices have been hired, they knead slower than grans
+    #[serde(default)]
+    pub(crate) apprentice_level: usize,
+    /// How many Cranking Cousins have been hired, each adds a level of autocranking
+    #[serde(default)]
+    pub(crate) cousin_level: usize,
+    /// How many Delivery Boys have been hired, each adds a bonus to the sale price
+    #[serde(default)]
+    pub(crate) delivery_boy_level: usize,
+    /// Egg pasta needs flour and cracked eggs for every dough ball, but sells for more
+    #[serde(default)]
+    pub(crate) egg_pasta: bool,
+    /// Each level of flour mill produces flour automatically
+    #[serde(default)]
+    pub(crate) flour_mill_level: usize,
+    /// Each level of egg cracker cracks eggs automatically
+    #[serde(default)]
+    pub(crate) egg_cracker_level: usize,
+    /// The cutter attachment turns sheets into tagliatelle
+    #[serde(default)]
+    pub(crate) has_cutter: bool,
+    /// The drying rack holds finished pasta until it has dried
+    #[serde(default)]
+    pub(crate) has_drying_rack: bool,
+    /// A rack hand moves dried pasta off the rack into stock automatically
+    #[serde(default)]
+    pub(crate) has_rack_hand: bool,
+    /// Each level adds more slots to the drying rack
+    #[serde(default)]
+    pub(crate) rack_slot_level: usize,
+    /// Pasta machines bought on top of the first one
+    #[serde(default)]
+    pub(crate) extra_machines: usize,
+    /// Upgrades for each machine, by index. Machines without an entry have no upgrades
+    #[serde(default)]
+    pub(crate) machines: Vec<MachineUpgrades>,
 }
 
 impl Default for CoreParameters {
     fn default() -> Self {
         Self {
             knead_tick_size: 0.02,
-            pasta_price: GameUInt::from(20usize),
+            pasta_price: GameDecimal::from_whole(20),
             auto_crank_level: 0,
             auto_knead_level: 0,
+            nonna_level: 0,
+            apprentice_level: 0,
+            cousin_level: 0,
+            delivery_boy_level: 0,
+            egg_pasta: false,
+            flour_mill_level: 0,
+            egg_cracker_level: 0,
+            has_cutter: false,
+            has_drying_rack: false,
+            has_rack_hand: false,
+            rack_slot_level: 0,
+            extra_machines: 0,
+            machines: Vec::new(),
+        }
+    }
+}
+
+impl CoreParameters {
+    /// Percentage added to the sale price by each delivery boy
+    const DELIVERY_BOY_BONUS_PCT: usize = 10;
+
+    /// Autocranking from upgrades combined with any cranking cousins
+    pub fn total_auto_crank_level(&self) -> usize {
+        self.auto_crank_level + self.cousin_level
+    }
+
+    /// Percentage added to the sale price by making egg pasta
+    const EGG_PASTA_BONUS_PCT: usize = 100;
+
+    /// What each finished sheet sells for, including the delivery boy and egg pasta bonuses
+    pub fn sale_price(&self) -> GameDecimal {
+        let mut bonus_pct = self.delivery_boy_level * Self::DELIVERY_BOY_BONUS_PCT;
+        if self.egg_pasta {
+            bonus_pct += Self::EGG_PASTA_BONUS_PCT;
+        }
+        self.pasta_price.clone() * GameDecimal::from_percent_increase(bonus_pct)
+    }
+
+    /// What one of the given pasta sells for
+    pub fn sale_price_of(&self, pasta: PastaType) -> GameDecimal {
+        self.sale_price() * pasta.price_multiplier()
+    }
+
+    pub fn machine_count(&self) -> usize {
+        1 + self.extra_machines
+    }
+
+    pub fn machine_upgrades(&self, index: usize) -> MachineUpgrades {
+        self.machines.get(index).cloned().unwrap_or_default()
+    }
+
+    /// Auto-crank level across all owned machines, from workers and motors together
+    pub fn kitchen_auto_crank_level(&self) -> usize {
+        let motors: usize = (0..self.machine_count())
+            .map(|i| self.machine_upgrades(i).motor_level)
+            .sum();
+        self.total_auto_crank_level() + motors
+    }
+
+    pub fn machine_upgrades_mut(&mut self, index: usize) -> &mut MachineUpgrades {
+        if self.machines.len() <= index {
+            self.machines.resize(index + 1, MachineUpgrades::default());
+        }
+        &mut self.machines[index]
+    }
+
+    /// What the machine produces from each dough ball
+    pub fn machine_output(&self) -> PastaType {
+        if self.has_cutter {
+            PastaType::Tagliatelle
+        } else {
+            PastaType::Sheet
         }
     }
 }
@@ -45,6 +158,21 @@ pub struct CoreState {
     pub(crate) money: GameUInt,
     pub(crate) diamonds: GameUInt,
     pub(crate) dough_balls: GameUInt,
+    /// Fractions of a coin earned from fractional prices, paid out into money once they add up
+    #[serde(default)]
+    pub(crate) money_fraction: GameDecimal,
+    /// Finished pasta waiting to be delivered or sold
+    #[serde(default)]
+    pub(crate) pasta_stock: PastaStock,
+    /// Flour for egg pasta, bought or milled
+    #[serde(default)]
+    pub(crate) flour: GameUInt,
+    /// Cracked eggs for egg pasta
+    #[serde(default)]
+    pub(crate) eggs: GameUInt,
+    /// Pasta hanging up to dry
+    #[serde(default)]
+    pub(crate) drying_rack: DryingRack,
 }
 
 impl Default for CoreState {
@@ -54,6 +182,11 @@ impl Default for CoreState {
             money: GameUInt::from(15000000usize),
             diamonds: GameUInt::from(42usize),
             dough_balls: GameUInt::from(5usize),
+            money_fraction: GameDecimal::default(),
+            pasta_stock: PastaStock::default(),
+            flour: GameUInt::default(),
+            eggs: GameUInt::default(),
+            drying_rack: DryingRack::default(),
         }
     }
     #[cfg(not(feature = "starting_money"))]
@@ -62,6 +195,11 @@ impl Default for CoreState {
             money: GameUInt::from(0usize),
             diamonds: GameUInt::from(0usize),
             dough_balls: GameUInt::from(0usize),
+            money_fraction: GameDecimal::default(),
+            pasta_stock: PastaStock::default(),
+            flour: GameUInt::default(),
+            eggs: GameUInt::default(),
+            drying_rack: DryingRack::default(),
         }
     }
 }
@@ -77,36 +215,201 @@ impl CoreState {
     pub fn add_money_big(&mut self, amount: GameUInt) {
         self.money += amount;
     }
+    pub fn add_money_decimal(&mut self, amount: GameDecimal) {
+        let total = self.money_fraction.clone() + amount;
+        self.money += total.floor();
+        self.money_fraction = total.fract();
+    }
+
+    /// Egg pasta needs flour and a cracked egg for each dough ball. Takes them and returns true if
+    /// there are enough, or if they aren't needed
+    pub fn try_use_dough_ingredients(&mut self, parameters: &CoreParameters) -> bool {
+        if !parameters.egg_pasta {
+            return true;
+        }
+        match (
+            self.flour.checked_sub(&GameUInt::one()),
+            self.eggs.checked_sub(&GameUInt::one()),
+        ) {
+            (Some(flour), Some(eggs)) => {
+                self.flour = flour;
+                self.eggs = eggs;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Hangs freshly made pasta on the drying rack if there is one with space, otherwise it goes
+    /// straight into stock
+    pub fn store_finished_pasta(&mut self, pasta: PastaType, parameters: &CoreParameters) {
+        let hung = parameters.has_drying_rack
+            && self
+                .drying_rack
+                .try_hang(pasta, DryingRack::capacity(parameters));
+        if !hung {
+            self.pasta_stock.add(pasta, 1);
+        }
+    }
+
+    /// Catches up on `secs` of time that passed while the game wasn't running
+    pub fn fast_forward(&mut self, parameters: &CoreParameters, secs: f32) {
+        let collected = self
+            .drying_rack
+            .fast_forward(secs, parameters.has_rack_hand);
+        for pasta in collected {
+            self.pasta_stock.add(pasta, 1);
+        }
+    }
+}
+
+/// The kinds of pasta that can be made, sold and ordered
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PastaType {
+    Sheet,
+    Tagliatelle,
+    DriedSheet,
+    DriedTagliatelle,
+    /// Left on the drying rack for too long
+    BrittleSheet,
+    BrittleTagliatelle,
+}
+
+impl PastaType {
+    pub const ALL: [PastaType; 6] = [
+        PastaType::Sheet,
+        PastaType::Tagliatelle,
+        PastaType::DriedSheet,
+        PastaType::DriedTagliatelle,
+        PastaType::BrittleSheet,
+        PastaType::BrittleTagliatelle,
+    ];
+
+    pub fn name(&self, quantity: usize) -> &'static str {
+        match (self, quantity) {
+            (PastaType::Sheet, 1) => "Sheet",
+            (PastaType::Sheet, _) => "Sheets",
+            (PastaType::Tagliatelle, _) => "Tagliatelle",
+            (PastaType::DriedSheet, 1) => "Dried Sheet",
+            (PastaType::DriedSheet, _) => "Dried Sheets",
+            (PastaType::DriedTagliatelle, _) => "Dried Tagliatelle",
+            (PastaType::BrittleSheet, 1) => "Brittle Sheet",
+            (PastaType::BrittleSheet, _) => "Brittle Sheets",
+            (PastaType::BrittleTagliatelle, _) => "Brittle Tagliatelle",
+        }
+    }
+
+    /// Multiplier on the base sale price
+    pub fn price_multiplier(&self) -> GameDecimal {
+        match self {
+            PastaType::Sheet => GameDecimal::one(),
+            PastaType::Tagliatelle => GameDecimal::from_ratio(3, 2),
+            PastaType::DriedSheet => GameDecimal::from_whole(2),
+            PastaType::DriedTagliatelle => GameDecimal::from_whole(3),
+            PastaType::BrittleSheet => GameDecimal::from_ratio(1, 2),
+            PastaType::BrittleTagliatelle => GameDecimal::from_ratio(3, 4),
+        }
+    }
+
+    /// What this pasta becomes once dried, if it can be
+    pub fn dried(&self) -> Option<PastaType> {
+        match self {
+            PastaType::Sheet => Some(PastaType::DriedSheet),
+            PastaType::Tagliatelle => Some(PastaType::DriedTagliatelle),
+            _ => None,
+        }
+    }
+
+    /// What this pasta becomes if it's left drying for too long, if it can be dried at all
+    pub fn over_dried(&self) -> Option<PastaType> {
+        match self {
+            PastaType::Sheet => Some(PastaType::BrittleSheet),
+            PastaType::Tagliatelle => Some(PastaType::BrittleTagliatelle),
+            _ => None,
+        }
+    }
+
+    /// The kinds of pasta the kitchen can currently make, customers only order these
+    pub fn available(parameters: &CoreParameters) -> Vec<PastaType> {
+        let mut available = Vec::new();
+        let made = parameters.machine_output();
+        available.push(made);
+        if parameters.has_drying_rack {
+            available.extend(made.dried());
+        }
+        available
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Count of finished pasta of each [PastaType]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PastaStock {
+    counts: Vec<usize>,
+}
+
+impl PastaStock {
+    pub fn get(&self, pasta: PastaType) -> usize {
+        self.counts.get(pasta.index()).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, pasta: PastaType, count: usize) {
+        if self.counts.len() <= pasta.index() {
+            self.counts.resize(pasta.index() + 1, 0);
+        }
+        self.counts[pasta.index()] += count;
+    }
+
+    /// Removes `count` of the given pasta if there is enough, returns whether it did
+    pub fn take(&mut self, pasta: PastaType, count: usize) -> bool {
+        if self.get(pasta) < count {
+            return false;
+        }
+        self.counts[pasta.index()] -= count;
+        true
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Empties the stock, returning how much of each pasta there was
+    pub fn take_all(&mut self) -> Vec<(PastaType, usize)> {
+        let taken = PastaType::ALL
+            .iter()
+            .map(|pasta| (*pasta, self.get(*pasta)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        self.counts.clear();
+        taken
+    }
 }
 
 #[derive(Debug)]
 pub struct IncrSprite {
     images: Vec<Bitmap>,
+    _sprites: SpriteOwner,
     sprite: Sprite,
     idx: Option<usize>,
     empty_bitmap: Bitmap,
 }
 
 impl IncrSprite {
-    pub fn new(
-        pos: (f32, f32),
-        base_name: &str,
-        num_images: usize,
-        sprite_tag: SpriteType,
-    ) -> Self {
+    pub fn new(pos: (f32, f32), base_name: &str, num_images: usize) -> Self {
         let graphics = Graphics::get();
         let images: Vec<Bitmap> = (0..num_images)
             .flat_map(|idx| graphics.load_bitmap(&format!("{}{}", base_name, idx)))
             .collect();
-        let sprite_manager = SpriteManager::get_mut();
-        let mut sprite = sprite_manager.new_sprite().unwrap();
+        let mut sprites = SpriteOwner::new();
+        let mut sprite = sprites.new_sprite();
         sprite
             .set_image(images[0].clone(), LCDBitmapFlip::kBitmapUnflipped)
             .unwrap();
-        sprite.set_tag(sprite_tag as u8).unwrap();
         let (x, y) = pos;
         sprite.move_to(x, y).unwrap();
-        sprite_manager.add_sprite(&sprite).unwrap();
         let empty_bitmap = {
             let size = Size2D::new(0, 0);
             graphics
@@ -115,6 +418,7 @@ impl IncrSprite {
         };
         Self {
             images,
+            _sprites: sprites,
             sprite,
             idx: Some(0),
             empty_bitmap,
@@ -133,35 +437,11 @@ impl IncrSprite {
         }
     }
 
-    pub fn reset(&mut self) {
-        self.idx = Some(0);
-        self.set_image();
-    }
-
-    pub fn incr(&mut self, wrap: bool) {
-        let prev = self.idx;
-        self.idx = match (self.idx, wrap) {
-            (Some(x), true) => Some((x + 1) % self.images.len()),
-            (Some(x), false) => {
-                let new_x = x + 1;
-                if new_x < self.images.len() {
-                    Some(new_x)
-                } else {
-                    None
-                }
-            }
-            (None, _) => None,
-        };
-        system::System::log_to_console(&format!("IncrSprite::incr {:?} -> {:?}", prev, self.idx));
-        self.set_image();
-    }
-
-    pub fn get_idx(&self) -> Option<usize> {
-        self.idx
-    }
-
-    pub fn is_active(&self) -> bool {
-        self.idx.is_some()
+    pub fn set_idx(&mut self, idx: Option<usize>) {
+        if self.idx != idx {
+            self.idx = idx;
+            self.set_image();
+        }
     }
 }
 
@@ -169,6 +449,8 @@ pub struct TextSpriteWithValue<V> {
     pub sprite: TextSprite,
     value: V,
     value_to_string: Box<dyn (Fn(&V) -> String)>,
+    /// The number format the text was last drawn with, so it's redrawn when the setting changes
+    shown_format: NumberFormat,
 }
 
 impl<V> Debug for TextSpriteWithValue<V>
@@ -190,18 +472,20 @@ impl<V: PartialEq + Clone> TextSpriteWithValue<V> {
             sprite,
             value,
             value_to_string,
+            shown_format: NumberFormat::current(),
         };
         t.update_sprite();
         t
     }
 
     fn update_sprite(&mut self) {
+        self.shown_format = NumberFormat::current();
         self.sprite
             .update_text(&(self.value_to_string)(&self.value))
             .unwrap();
     }
     pub fn update_value(&mut self, value: &V) {
-        if *value != self.value {
+        if *value != self.value || self.shown_format != NumberFormat::current() {
             self.value = value.clone();
             self.update_sprite();
         }
@@ -304,39 +588,77 @@ impl Timer {
     }
 }
 
+/// Splits real time into fixed size steps, so the economy advances at the same rate however often
+/// frames come in
 #[derive(Debug)]
-pub struct AutoTicker {
-    level: usize,
+pub struct FixedStep {
+    step: f32,
+    /// Most steps run in one frame. Any more time than that is dropped, so coming back from a long
+    /// pause doesn't stall the game while it catches up
+    max_steps: usize,
+    accumulator: f32,
     last_tick: f32,
-    /** Rate is "degrees per sec", and is multiplied by level */
-    base_rate: f32,
 }
 
-impl AutoTicker {
-    pub fn new(base_rate: f32) -> Self {
+impl FixedStep {
+    pub fn new(step: f32, max_steps: usize) -> Self {
         Self {
-            level: 0,
+            step,
+            max_steps,
+            accumulator: 0.0,
             last_tick: System::get().get_elapsed_time().unwrap_or(0.0),
-            base_rate,
         }
     }
 
-    fn get_rate(&self) -> f32 {
-        // Rate is "degrees per sec"
-        self.level as f32 * self.base_rate
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Drops any time built up so far, e.g. after the game has been paused
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last_tick = System::get().get_elapsed_time().unwrap_or(self.last_tick);
     }
-    pub fn poll(&mut self, level: usize) -> f32 {
-        if self.level != level {
-            self.level = level;
-        }
-        if level == 0 {
-            return 0.0;
-        }
 
+    /// Adds the time since the last call and returns how many steps to run for it
+    pub fn advance(&mut self) -> usize {
         let now = System::get().get_elapsed_time().unwrap_or(self.last_tick);
-        let rate = self.get_rate();
-        let time_diff = now - self.last_tick;
+        self.accumulator += (now - self.last_tick).max(0.0);
         self.last_tick = now;
-        time_diff * rate
+        let steps = (self.accumulator / self.step) as usize;
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            return self.max_steps;
+        }
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    /// How far the current frame is between the last step and the next, from 0.0 to 1.0. Visuals
+    /// blend between the last two steps by this, so they move smoothly between steps
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+/// Blends from `from` to `to`, with `t` from 0.0 to 1.0
+pub fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+#[derive(Debug)]
+pub struct AutoTicker {
+    /** Rate is "degrees per sec", and is multiplied by level */
+    base_rate: f32,
+}
+
+impl AutoTicker {
+    pub fn new(base_rate: f32) -> Self {
+        Self { base_rate }
+    }
+
+    /// Amount ticked over `dt` seconds at `level`
+    pub fn poll(&self, level: usize, dt: f32) -> f32 {
+        level as f32 * self.base_rate * dt
     }
 }
@@ -0,0 +1,80 @@
+use crankstart::log_to_console;
+use crankstart::sound::{Sound, Synth};
+use crankstart::system::System;
+use crankstart_sys::SoundWaveform;
+
+/// The ratchet click of the machine's gears, synthesised so it keeps up with any crank speed
+/// rather than replaying a sample. Each gear tooth passed makes a click, getting louder and higher
+/// as the crank turns faster
+#[derive(Debug)]
+pub struct CrankSound {
+    /// None if a synth couldn't be made, in which case cranking is silent
+    synth: Option<Synth>,
+    /// How far the crank is towards the next tooth, from 0.0 to 1.0
+    tooth_progress: f32,
+    /// From 0.0 to 1.0, set from the settings
+    volume: f32,
+    last_tick: f32,
+}
+
+impl CrankSound {
+    const DEGREES_PER_TOOTH: f32 = 15.0;
+    /// Crank speed at which clicks reach full volume and pitch
+    const FULL_SPEED_DEGREES_PER_SEC: f32 = 720.0;
+    const MIN_FREQUENCY: f32 = 600.0;
+    const MAX_FREQUENCY: f32 = 1400.0;
+    /// Volume of the slowest clicks, as a fraction of full volume
+    const MIN_VELOCITY: f32 = 0.25;
+    const CLICK_SECS: f32 = 0.02;
+
+    pub fn new() -> Self {
+        let synth = Sound::get()
+            .get_synth()
+            .and_then(|synth| {
+                synth.set_waveform(SoundWaveform::kWaveformNoise)?;
+                synth.set_attack_time(0.0)?;
+                synth.set_decay_time(Self::CLICK_SECS)?;
+                synth.set_sustain_level(0.0)?;
+                synth.set_release_time(0.0)?;
+                Ok(synth)
+            })
+            .map_err(|e| log_to_console!("Failed to make crank synth: {:?}", e))
+            .ok();
+        Self {
+            synth,
+            tooth_progress: 0.0,
+            volume: 1.0,
+            last_tick: System::get().get_elapsed_time().unwrap_or(0.0),
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// `crank_move` is how many degrees the crank turned this frame, by hand or by motor
+    pub fn update(&mut self, crank_move: f32) {
+        let now = System::get().get_elapsed_time().unwrap_or(self.last_tick);
+        let dt = now - self.last_tick;
+        self.last_tick = now;
+        let synth = match &self.synth {
+            Some(synth) if dt > 0.0 && self.volume > 0.0 => synth,
+            _ => return,
+        };
+
+        self.tooth_progress += crank_move.abs() / Self::DEGREES_PER_TOOTH;
+        if self.tooth_progress < 1.0 {
+            return;
+        }
+        // Teeth passed within the same frame blur into one click, fast cranking is heard as a
+        // click every frame instead
+        self.tooth_progress %= 1.0;
+
+        let speed = (crank_move.abs() / dt / Self::FULL_SPEED_DEGREES_PER_SEC).min(1.0);
+        let frequency = Self::MIN_FREQUENCY + (Self::MAX_FREQUENCY - Self::MIN_FREQUENCY) * speed;
+        let velocity = self.volume * (Self::MIN_VELOCITY + (1.0 - Self::MIN_VELOCITY) * speed);
+        if let Err(e) = synth.play_note(frequency, velocity, Self::CLICK_SECS, 0) {
+            log_to_console!("Failed to play crank click: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::game_events::{GameEvent, GameEventSubscriber};
+use crate::game_value::GameValue;
+use crate::helpers::Rng;
+use crate::sprite_owner::SpriteOwner;
+use alloc::format;
+use alloc::vec::Vec;
+use crankstart::graphics::LCDColor;
+use crankstart::sprite::{Sprite, TextSprite};
+use crankstart::system::System;
+use crankstart_sys::LCDSolidColor;
+
+/// Above the kitchen and bottom bar, below full screen panels like the settings
+const PARTICLE_Z_INDEX: i16 = 30;
+
+const FLOUR_PILE_POS: (f32, f32) = (80.0, 80.0);
+/// Where the coin icon sits in the bottom bar
+const COIN_ICON_POS: (f32, f32) = (290.0, 216.0);
+
+#[derive(Debug, Copy, Clone)]
+enum Motion {
+    /// Moves at a constant velocity, in pixels per second
+    Drift { velocity: (f32, f32) },
+    /// Eases from the start to `to` over the particle's lifetime
+    FlyTo { to: (f32, f32) },
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    start: (f32, f32),
+    motion: Motion,
+    age: f32,
+    lifetime: f32,
+}
+
+impl Particle {
+    fn pos(&self) -> (f32, f32) {
+        match self.motion {
+            Motion::Drift { velocity } => (
+                self.start.0 + velocity.0 * self.age,
+                self.start.1 + velocity.1 * self.age,
+            ),
+            Motion::FlyTo { to } => {
+                // Ease out, so it rushes off and settles into place
+                let t = (self.age / self.lifetime).clamp(0.0, 1.0);
+                let eased = 1.0 - (1.0 - t) * (1.0 - t);
+                (
+                    self.start.0 + (to.0 - self.start.0) * eased,
+                    self.start.1 + (to.1 - self.start.1) * eased,
+                )
+            }
+        }
+    }
+}
+
+/// Gives the pool access to the sprite behind each kind of particle
+trait ParticleSprite {
+    fn sprite_mut(&mut self) -> &mut Sprite;
+}
+
+impl ParticleSprite for Sprite {
+    fn sprite_mut(&mut self) -> &mut Sprite {
+        self
+    }
+}
+
+impl ParticleSprite for TextSprite {
+    fn sprite_mut(&mut self) -> &mut Sprite {
+        self.get_sprite_mut()
+    }
+}
+
+/// A fixed set of sprites that particles are shown with. Sprites are made up front and reused, and
+/// once they're all in use new particles are dropped, so a burst of events can't blow the frame
+/// budget
+#[derive(Debug)]
+struct ParticlePool<S> {
+    slots: Vec<(S, Option<Particle>)>,
+}
+
+impl<S: ParticleSprite> ParticlePool<S> {
+    fn new(sprites: Vec<S>) -> Self {
+        let slots = sprites
+            .into_iter()
+            .map(|mut sprite| {
+                let s = sprite.sprite_mut();
+                s.set_z_index(PARTICLE_Z_INDEX).unwrap();
+                s.set_visible(false).unwrap();
+                (sprite, None)
+            })
+            .collect();
+        Self { slots }
+    }
+
+    /// Returns the sprite the particle was given, if there was a free one. A particle with a
+    /// negative age stays hidden until its age reaches zero
+    fn spawn(&mut self, particle: Particle) -> Option<&mut S> {
+        let (sprite, slot) = self.slots.iter_mut().find(|(_, slot)| slot.is_none())?;
+        *slot = Some(particle);
+        let (x, y) = particle.start;
+        let s = sprite.sprite_mut();
+        s.move_to(x, y).unwrap();
+        s.set_visible(particle.age >= 0.0).unwrap();
+        Some(sprite)
+    }
+
+    fn update(&mut self, dt: f32) {
+        for (sprite, slot) in self.slots.iter_mut() {
+            let particle = match slot {
+                Some(particle) => particle,
+                None => continue,
+            };
+            let was_waiting = particle.age < 0.0;
+            particle.age += dt;
+            if particle.age < 0.0 {
+                continue;
+            }
+            let s = sprite.sprite_mut();
+            if was_waiting {
+                s.set_visible(true).unwrap();
+            }
+            if particle.age >= particle.lifetime {
+                s.set_visible(false).unwrap();
+                *slot = None;
+            } else {
+                let (x, y) = particle.pos();
+                s.move_to(x, y).unwrap();
+            }
+        }
+    }
+}
+
+/// Short-lived feedback for things happening in the kitchen: money floating up from the machine,
+/// coins flying into the bottom bar and flour puffing off the pile while kneading
+#[derive(Debug)]
+pub struct Effects {
+    _sprites: SpriteOwner,
+    money_text: ParticlePool<TextSprite>,
+    coins: ParticlePool<Sprite>,
+    puffs: ParticlePool<Sprite>,
+    rng: Rng,
+    last_tick: f32,
+}
+
+impl Effects {
+    const MAX_MONEY_TEXT: usize = 4;
+    const MAX_COINS: usize = 8;
+    const MAX_PUFFS: usize = 6;
+    /// Most coins sent flying for a single payment
+    const COINS_PER_PAYMENT: usize = 3;
+
+    pub fn new() -> Self {
+        let mut sprites = SpriteOwner::new();
+        let money_text = (0..Self::MAX_MONEY_TEXT)
+            .map(|_| sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack)))
+            .collect();
+        let coins = (0..Self::MAX_COINS)
+            .map(|_| sprites.load_sprite_at("res/coin", COIN_ICON_POS.0, COIN_ICON_POS.1))
+            .collect();
+        let puffs = (0..Self::MAX_PUFFS)
+            .map(|_| sprites.load_sprite_at("res/flour_puff", FLOUR_PILE_POS.0, FLOUR_PILE_POS.1))
+            .collect();
+        Self {
+            _sprites: sprites,
+            money_text: ParticlePool::new(money_text),
+            coins: ParticlePool::new(coins),
+            puffs: ParticlePool::new(puffs),
+            rng: Rng::from_time(),
+            last_tick: System::get().get_elapsed_time().unwrap_or(0.0),
+        }
+    }
+
+    /// Floats `text` up from `at` and sends coins flying from there to the bottom bar
+    fn spawn_money(&mut self, text: &str, at: (f32, f32)) {
+        let particle = Particle {
+            start: at,
+            motion: Motion::Drift {
+                velocity: (0.0, -30.0),
+            },
+            age: 0.0,
+            lifetime: 1.2,
+        };
+        if let Some(sprite) = self.money_text.spawn(particle) {
+            sprite.update_text(text).unwrap();
+        }
+        for i in 0..Self::COINS_PER_PAYMENT {
+            let start = (
+                at.0 + self.rng.range_f32(-12.0, 12.0),
+                at.1 + self.rng.range_f32(-6.0, 6.0),
+            );
+            self.coins.spawn(Particle {
+                start,
+                motion: Motion::FlyTo { to: COIN_ICON_POS },
+                // Staggered, so the coins land one after another
+                age: -0.1 * i as f32,
+                lifetime: 0.6,
+            });
+        }
+    }
+
+    fn spawn_puff(&mut self) {
+        let velocity = (
+            self.rng.range_f32(-40.0, 40.0),
+            self.rng.range_f32(-50.0, -20.0),
+        );
+        self.puffs.spawn(Particle {
+            start: FLOUR_PILE_POS,
+            motion: Motion::Drift { velocity },
+            age: 0.0,
+            lifetime: 0.4,
+        });
+    }
+
+    pub fn update(&mut self) {
+        let now = System::get().get_elapsed_time().unwrap_or(self.last_tick);
+        let dt = now - self.last_tick;
+        self.last_tick = now;
+        self.money_text.update(dt);
+        self.coins.update(dt);
+        self.puffs.update(dt);
+    }
+}
+
+impl GameEventSubscriber for Effects {
+    fn on_events(&mut self, events: &[GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::MoneyGained { amount, at } => {
+                    self.spawn_money(&format!("+{}", amount.to_string_hum()), *at)
+                }
+                GameEvent::Kneaded => self.spawn_puff(),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::core_elements::{CoreParameters, CoreState, TextSpriteWithValue};
+use crate::game_events::{GameEvent, GameEventBus, GameEventSubscriber};
+use crate::game_value::{GameDecimal, GameUInt};
+use crate::helpers::Rng;
+use crate::sprite_owner::SpriteOwner;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use crankstart::graphics::LCDColor;
+use crankstart::log_to_console;
+use crankstart::sprite::Sprite;
+use crankstart::system::System;
+use crankstart_sys::{LCDSolidColor, PDButtons};
+use serde::{Deserialize, Serialize};
+
+/// Timed opportunities (and the odd setback) that turn up every so often to give a reason to keep
+/// an eye on the kitchen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+    /// A golden dough ball on the flour pile, grab it with B for a sale price boost
+    GoldenDough,
+    /// A customer wants a number of sheets made before a deadline, and pays a bonus for them
+    RushOrder,
+    /// Kneading is slowed for a while
+    FlourShortage,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum EventPhase {
+    /// Waiting on the player to act before it runs out
+    Offered,
+    /// The effect is running
+    Active,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CurrentEvent {
+    kind: EventKind,
+    phase: EventPhase,
+    /// Seconds left in the current phase
+    remaining: f32,
+    /// Only used by rush orders
+    sheets_done: usize,
+    sheets_wanted: usize,
+}
+
+/// The saved part of [RandomEvents]. Times are stored as seconds remaining rather than absolute
+/// times, as elapsed time restarts with the game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsState {
+    rng: Rng,
+    /// Seconds until the next event is due
+    next_event_in: f32,
+    current: Option<CurrentEvent>,
+}
+
+impl Default for EventsState {
+    fn default() -> Self {
+        let mut rng = Rng::from_time();
+        let next_event_in = RandomEvents::next_gap(&mut rng);
+        Self {
+            rng,
+            next_event_in,
+            current: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RandomEvents {
+    state: EventsState,
+    _sprites: SpriteOwner,
+    golden_dough: Sprite,
+    customer: Sprite,
+    flour_shortage: Sprite,
+    status_text: TextSpriteWithValue<String>,
+}
+
+impl RandomEvents {
+    const MIN_GAP: f32 = 60.0;
+    const MAX_GAP: f32 = 180.0;
+    const GOLDEN_OFFER_SECS: f32 = 8.0;
+    const GOLDEN_BOOST_SECS: f32 = 30.0;
+    const GOLDEN_PRICE_MULTIPLIER: usize = 7;
+    const RUSH_SECS_PER_SHEET: f32 = 12.0;
+    /// Rush orders pay this many times the sale price per sheet on completion
+    const RUSH_BONUS_MULTIPLIER: usize = 3;
+    const SHORTAGE_SECS: f32 = 20.0;
+
+    pub fn new(state: EventsState) -> Self {
+        let mut sprites = SpriteOwner::new();
+        let mut golden_dough = sprites.load_sprite_at("res/golden_dough", 80.0, 70.0);
+        golden_dough.set_z_index(5).unwrap();
+        let customer = sprites.load_sprite_at("res/customer", 195.0, 80.0);
+        let flour_shortage = sprites.load_sprite_at("res/flour_shortage", 165.0, 35.0);
+        let mut status_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        status_text.get_sprite_mut().move_to(200.0, 8.0).unwrap();
+        let status_text =
+            TextSpriteWithValue::new(status_text, String::new(), Box::new(String::clone));
+        let mut s = Self {
+            state,
+            _sprites: sprites,
+            golden_dough,
+            customer,
+            flour_shortage,
+            status_text,
+        };
+        s.update_visuals();
+        s
+    }
+
+    pub fn to_saved(&self) -> EventsState {
+        self.state.clone()
+    }
+
+    fn next_gap(rng: &mut Rng) -> f32 {
+        rng.range_f32(Self::MIN_GAP, Self::MAX_GAP)
+    }
+
+    fn current_is(&self, kind: EventKind, phase: EventPhase) -> bool {
+        match &self.state.current {
+            Some(current) => current.kind == kind && current.phase == phase,
+            None => false,
+        }
+    }
+
+    /// True while an event is offered or running
+    pub fn is_running(&self) -> bool {
+        self.state.current.is_some()
+    }
+
+    /// Multiplier on the sale price of each sheet
+    pub fn price_multiplier(&self) -> GameDecimal {
+        if self.current_is(EventKind::GoldenDough, EventPhase::Active) {
+            GameDecimal::from_whole(Self::GOLDEN_PRICE_MULTIPLIER)
+        } else {
+            GameDecimal::one()
+        }
+    }
+
+    /// Multiplier on all kneading, both by hand and by workers
+    pub fn knead_multiplier(&self) -> f32 {
+        if self.current_is(EventKind::FlourShortage, EventPhase::Active) {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    fn start_event(&mut self, game_events: &mut GameEventBus) {
+        let rng = &mut self.state.rng;
+        let roll = rng.next_f32();
+        let event = if roll < 0.5 {
+            CurrentEvent {
+                kind: EventKind::GoldenDough,
+                phase: EventPhase::Offered,
+                remaining: Self::GOLDEN_OFFER_SECS,
+                sheets_done: 0,
+                sheets_wanted: 0,
+            }
+        } else if roll < 0.8 {
+            let sheets_wanted = rng.range_usize(3, 8);
+            CurrentEvent {
+                kind: EventKind::RushOrder,
+                phase: EventPhase::Active,
+                remaining: sheets_wanted as f32 * Self::RUSH_SECS_PER_SHEET,
+                sheets_done: 0,
+                sheets_wanted,
+            }
+        } else {
+            CurrentEvent {
+                kind: EventKind::FlourShortage,
+                phase: EventPhase::Active,
+                remaining: Self::SHORTAGE_SECS,
+                sheets_done: 0,
+                sheets_wanted: 0,
+            }
+        };
+        log_to_console!("Starting event {:?}", event.kind);
+        game_events.push(GameEvent::RandomEventStarted(event.kind));
+        self.state.current = Some(event);
+    }
+
+    fn finish_event(&mut self) {
+        self.state.current = None;
+        self.state.next_event_in = Self::next_gap(&mut self.state.rng);
+    }
+
+    fn step_current(
+        &mut self,
+        dt: f32,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        game_events: &mut GameEventBus,
+    ) {
+        let current = match &mut self.state.current {
+            Some(current) => current,
+            None => return,
+        };
+        current.remaining -= dt;
+        match (current.kind, current.phase) {
+            (EventKind::RushOrder, _) if current.sheets_done >= current.sheets_wanted => {
+                let bonus = GameUInt::from(current.sheets_wanted * Self::RUSH_BONUS_MULTIPLIER);
+                let amount = parameters.sale_price() * GameDecimal::from(bonus);
+                game_events.push(GameEvent::MoneyGained {
+                    amount: amount.floor(),
+                    at: self.customer.get_position().unwrap(),
+                });
+                state.add_money_decimal(amount);
+                self.finish_event();
+            }
+            _ if current.remaining <= 0.0 => self.finish_event(),
+            _ => {}
+        }
+    }
+
+    fn status_string(&self) -> String {
+        let current = match &self.state.current {
+            Some(current) => current,
+            None => return String::new(),
+        };
+        let secs = current.remaining.max(0.0) as usize;
+        match (current.kind, current.phase) {
+            (EventKind::GoldenDough, EventPhase::Offered) => {
+                format!("Golden dough! Press B ({}s)", secs)
+            }
+            (EventKind::GoldenDough, EventPhase::Active) => {
+                format!("x{} sale price ({}s)", Self::GOLDEN_PRICE_MULTIPLIER, secs)
+            }
+            (EventKind::RushOrder, _) => format!(
+                "Rush order: {}/{} sheets ({}s)",
+                current.sheets_done, current.sheets_wanted, secs
+            ),
+            (EventKind::FlourShortage, _) => format!("Flour shortage! ({}s)", secs),
+        }
+    }
+
+    fn update_visuals(&mut self) {
+        let kind = self.state.current.as_ref().map(|current| current.kind);
+        let golden_offered = self.current_is(EventKind::GoldenDough, EventPhase::Offered);
+        self.golden_dough.set_visible(golden_offered).unwrap();
+        self.customer
+            .set_visible(kind == Some(EventKind::RushOrder))
+            .unwrap();
+        self.flour_shortage
+            .set_visible(kind == Some(EventKind::FlourShortage))
+            .unwrap();
+        let status = self.status_string();
+        self.status_text.update_value(&status);
+    }
+
+    /// Advances the timers of the current event, or towards the next one
+    pub fn step(
+        &mut self,
+        dt: f32,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        game_events: &mut GameEventBus,
+    ) {
+        if self.state.current.is_some() {
+            self.step_current(dt, state, parameters, game_events);
+        } else {
+            self.state.next_event_in -= dt;
+            if self.state.next_event_in <= 0.0 {
+                self.start_event(game_events);
+            }
+        }
+    }
+
+    /// Handles grabbing the golden dough with B. Input is read every frame rather than every step,
+    /// so a press is never missed or counted twice
+    pub fn update(&mut self, game_events: &mut GameEventBus) {
+        let (_, pressed, _) = System::get().get_button_state().unwrap();
+        let b_pressed = (pressed & PDButtons::kButtonB).0 != 0;
+        if let Some(current) = &mut self.state.current {
+            if b_pressed
+                && current.kind == EventKind::GoldenDough
+                && current.phase == EventPhase::Offered
+            {
+                game_events.push(GameEvent::GoldenDoughTaken);
+                current.phase = EventPhase::Active;
+                current.remaining = Self::GOLDEN_BOOST_SECS;
+            }
+        }
+        self.update_visuals();
+    }
+}
+
+impl GameEventSubscriber for RandomEvents {
+    /// Counts sheets made towards a rush order. Each event is one sheet, however many pieces the
+    /// rollers cut it into
+    fn on_events(&mut self, events: &[GameEvent]) {
+        let current = match &mut self.state.current {
+            Some(current) if current.kind == EventKind::RushOrder => current,
+            _ => return,
+        };
+        for event in events {
+            if let GameEvent::SheetMade { .. } = event {
+                current.sheets_done += 1;
+            }
+        }
+    }
+}
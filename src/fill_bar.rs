@@ -1,5 +1,5 @@
-use crate::helpers::load_sprite_at;
-use crate::SpriteType;
+use crate::sprite_owner::SpriteOwner;
+use crate::tween::{Easing, Tween};
 use alloc::format;
 use anyhow::Error;
 use crankstart::geometry::{ScreenPoint, ScreenRect};
@@ -7,25 +7,26 @@ use crankstart::graphics::{Graphics, LCDColor};
 use crankstart::log_to_console;
 use crankstart::sprite::Sprite;
 use crankstart::system::System;
-use crankstart_sys::{LCDBitmapFlip, LCDPattern, LCDSolidColor};
+use crankstart_sys::{LCDBitmapFlip, LCDPattern, LCDSolidColor, PDRect};
 use euclid::{Point2D, Rect, Size2D};
 
 #[derive(Debug)]
 pub struct FillBar {
     fill_pct: f32,
+    /// The fill that's drawn, easing up to `fill_pct`
+    shown_pct: Tween,
+    _sprites: SpriteOwner,
     background_sprite: Sprite,
     full_fill_rect: ScreenRect,
     dirty: bool,
 }
 
 impl FillBar {
+    const FILL_SECS: f32 = 0.15;
+
     pub fn new(pos: (f32, f32)) -> Self {
-        let mut background_sprite = load_sprite_at(
-            "res/bar_background",
-            pos.0,
-            pos.1,
-            Some(SpriteType::FillBar as u8),
-        );
+        let mut sprites = SpriteOwner::new();
+        let mut background_sprite = sprites.load_sprite_at("res/bar_background", pos.0, pos.1);
         let full_fill_rect = {
             let origin = Point2D::new(pos.0 as i32 - 3, pos.1 as i32 - 16);
             Rect::new(origin, Size2D::new(5, 32))
@@ -33,6 +34,8 @@ impl FillBar {
         background_sprite.set_use_custom_draw().unwrap();
         Self {
             fill_pct: 0.0,
+            shown_pct: Tween::settled(0.0, Self::FILL_SECS, Easing::EaseOut),
+            _sprites: sprites,
             background_sprite,
             full_fill_rect,
             dirty: true,
@@ -40,7 +43,7 @@ impl FillBar {
     }
 
     pub fn update(&mut self) {
-        if self.dirty {
+        if self.dirty || !self.shown_pct.is_finished() {
             self.background_sprite.mark_dirty().unwrap();
             self.dirty = false;
         }
@@ -48,7 +51,7 @@ impl FillBar {
 
     pub fn get_fill_rect(&self) -> ScreenRect {
         // Scale height by fill pct, and move origin down by that amount too so bottom left is fixed
-        let height = (self.full_fill_rect.size.height as f32 * self.fill_pct) as i32;
+        let height = (self.full_fill_rect.size.height as f32 * self.shown_pct.value()) as i32;
         let y_diff = self.full_fill_rect.size.height - height;
         let mut rect = self.full_fill_rect.clone();
         rect.origin.y += y_diff;
@@ -56,14 +59,29 @@ impl FillBar {
         rect
     }
 
+    /// Filling up eases in, emptying is shown straight away
     pub fn set_fill_pct(&mut self, pct: f32) {
-        self.fill_pct = f32::clamp(pct, 0.0, 1.0);
+        let pct = f32::clamp(pct, 0.0, 1.0);
+        if pct > self.fill_pct {
+            self.shown_pct.retarget(pct);
+        } else {
+            self.shown_pct = Tween::settled(pct, Self::FILL_SECS, Easing::EaseOut);
+        }
+        self.fill_pct = pct;
         self.dirty = true;
     }
 
     pub fn incr_fill_pct(&mut self, pct: f32) {
         self.set_fill_pct(self.fill_pct + pct);
     }
+    pub fn sprite_mut(&mut self) -> &mut Sprite {
+        &mut self.background_sprite
+    }
+
+    pub fn bounds(&self) -> PDRect {
+        self.background_sprite.get_bounds().unwrap()
+    }
+
     pub fn get_fill_pct(&self) -> f32 {
         self.fill_pct
     }
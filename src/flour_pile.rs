@@ -1,99 +1,55 @@
-use crate::audio_events::{AudioEvent, AudioEventChannel};
-use crate::core_elements::AutoTicker;
+use crate::events::RandomEvents;
 use crate::fill_bar::FillBar;
-use crate::helpers::load_sprite_at;
-use crate::{CoreParameters, CoreState, GameUInt, SpriteType};
-use alloc::vec::Vec;
-use anyhow::Error;
+use crate::game_events::{GameEvent, GameEventBus};
+use crate::sprite_owner::SpriteOwner;
+use crate::sprite_registry::SpriteRegistry;
+use crate::workers::Workers;
+use crate::{CoreParameters, CoreState, GameState, GameUInt};
 use crankstart::graphics::{Bitmap, Graphics};
 use crankstart::log_to_console;
-use crankstart::sprite::{Sprite, SpriteManager};
+use crankstart::sprite::Sprite;
 use crankstart::system::System;
-use crankstart_sys::{LCDBitmapFlip, PDButtons};
+use crankstart_sys::{LCDBitmapFlip, PDButtons, PDRect};
 
 #[derive(Debug)]
 pub struct FlourPile {
+    _sprites: SpriteOwner,
     sprite: Sprite,
     button_indicator: AButtonIndicator,
     fill_bar: FillBar,
-    auto_knead: AutoTicker,
-    kneading_grans: KneadingGrans,
-}
-
-#[derive(Debug)]
-struct KneadingGrans {
-    sprites: Vec<Sprite>,
-    image: Bitmap,
-    level: usize,
-    midpoint: (f32, f32),
-}
-
-impl KneadingGrans {
-    fn new(x: f32, y: f32) -> Self {
-        let sprites = Vec::new();
-        let image = Graphics::get().load_bitmap("res/gran").unwrap();
-        Self {
-            sprites,
-            image,
-            level: 0,
-            midpoint: (x, y),
-        }
-    }
-
-    fn set_level(&mut self, level: usize) {
-        if level == self.level {
-            return;
-        }
-        self.sprites.clear();
-        let sprite_manager = SpriteManager::get_mut();
-        for i in 0..level {
-            let centred_i = i as i32 - (level as i32 / 2);
-            let mut sprite = sprite_manager.new_sprite().unwrap();
-            sprite
-                .set_image(self.image.clone(), LCDBitmapFlip::kBitmapUnflipped)
-                .unwrap();
-            // TODO: Centre on midpoint properly
-            sprite
-                .move_to(self.midpoint.0 + (centred_i as f32 * 20.0), self.midpoint.1)
-                .unwrap();
-            sprite_manager.add_sprite(&sprite).unwrap();
-            self.sprites.push(sprite);
-        }
-        self.level = level;
-    }
+    workers: Workers,
 }
 
 impl FlourPile {
     pub fn new(pos: (f32, f32)) -> Self {
-        let sprite = load_sprite_at(
-            "res/flour_pile",
-            pos.0,
-            pos.1,
-            Some(SpriteType::FlourPile as u8),
-        );
-        {
-            log_to_console!("TEST!!!");
-            let level = 19usize;
-            for i in 0..level {
-                let centred_i = i as i32 - (level as i32 / 2);
-                log_to_console!("i: {}, centred_i: {}", i, centred_i);
-            }
-        }
-        let button_indicator = AButtonIndicator::new((pos.0 + 30.0, pos.1 - 45.0));
+        let mut sprites = SpriteOwner::new();
+        let sprite = sprites.load_sprite_at("res/flour_pile", pos.0, pos.1);
+        let button_indicator = AButtonIndicator::new((pos.0 + 30.0, pos.1 - 45.0), &mut sprites);
         let fill_bar = FillBar::new((pos.0 + 55.0, pos.1 - 45.0));
-        let auto_knead = AutoTicker::new(0.02);
-        let kneading_grans = KneadingGrans::new(pos.0, pos.1 + 45.0);
+        let workers = Workers::new(pos.0, pos.1 + 45.0);
         Self {
+            _sprites: sprites,
             sprite,
             button_indicator,
             fill_bar,
-            auto_knead,
-            kneading_grans,
+            workers,
         }
     }
 
-    pub fn fill_bar_update(&mut self) {
-        self.fill_bar.update();
+    pub fn fill_pct(&self) -> f32 {
+        self.fill_bar.get_fill_pct()
+    }
+
+    pub fn set_fill_pct(&mut self, fill_pct: f32) {
+        self.fill_bar.set_fill_pct(fill_pct);
+    }
+
+    pub fn button_bounds(&self) -> PDRect {
+        self.button_indicator.sprite.get_bounds().unwrap()
+    }
+
+    pub fn fill_bar_bounds(&self) -> PDRect {
+        self.fill_bar.bounds()
     }
 
     fn tick(&mut self, tick_size: f32) {
@@ -101,36 +57,61 @@ impl FlourPile {
         self.fill_bar.update();
     }
 
+    /// Lets the hired workers knead for `dt` seconds
+    pub fn step(
+        &mut self,
+        dt: f32,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+        game_events: &mut GameEventBus,
+    ) {
+        let auto_knead = self.workers.step(dt, parameters) * random_events.knead_multiplier();
+        self.tick(auto_knead);
+        self.make_dough(state, parameters, game_events);
+    }
+
+    /// Kneading by hand. `alpha` is how far the frame is between economy steps, and
+    /// `input_enabled` is false while another panel (e.g. the shop) is taking button presses
     pub fn update(
         &mut self,
         state: &mut CoreState,
         parameters: &CoreParameters,
-        events: &mut AudioEventChannel,
+        random_events: &RandomEvents,
+        game_events: &mut GameEventBus,
+        alpha: f32,
+        input_enabled: bool,
     ) {
-        self.kneading_grans.set_level(parameters.auto_knead_level);
-        let auto_knead = self.auto_knead.poll(parameters.auto_knead_level);
-        // TODO: Disable input if menu is open ...
+        self.workers.update(alpha);
+        let knead_tick_size = parameters.knead_tick_size * random_events.knead_multiplier();
         let (_, pressed, released) = System::get().get_button_state().unwrap();
-        if (pressed & PDButtons::kButtonA).0 != 0 {
+        if input_enabled && (pressed & PDButtons::kButtonA).0 != 0 {
             self.button_indicator.set_pressed();
-            self.tick(parameters.knead_tick_size);
+            self.tick(knead_tick_size);
+            game_events.push(GameEvent::Kneaded);
         } else if (released & PDButtons::kButtonA).0 != 0 {
+            // Always let go of the button, in case a panel opened while it was held
             self.button_indicator.set_unpressed();
-            self.tick(parameters.knead_tick_size);
-        } else {
-            self.tick(auto_knead);
+            if input_enabled {
+                self.tick(knead_tick_size);
+            }
         }
+        self.make_dough(state, parameters, game_events);
+    }
 
-        if self.is_full() {
-            events.push(AudioEvent::DoughCreated);
+    fn make_dough(
+        &mut self,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        game_events: &mut GameEventBus,
+    ) {
+        // With egg pasta the bar stays full until there's flour and an egg to make the dough with
+        if self.is_full() && state.try_use_dough_ingredients(parameters) {
+            game_events.push(GameEvent::DoughCreated);
             state.dough_balls += GameUInt::one();
             self.reset();
         }
     }
-    pub fn draw_fill_bar(&self) -> Result<(), Error> {
-        self.fill_bar.draw()
-    }
-
     fn is_full(&self) -> bool {
         self.fill_bar.get_fill_pct() > 0.99
     }
@@ -138,6 +119,22 @@ impl FlourPile {
     fn reset(&mut self) {
         self.fill_bar.set_fill_pct(0.0);
     }
+
+    pub fn register_sprites(&mut self, registry: &mut SpriteRegistry<GameState>) {
+        registry.on_update(&mut self.sprite, |game| {
+            game.flour_pile.update(
+                &mut game.state,
+                &game.parameters,
+                &game.random_events,
+                &mut game.game_events,
+                game.economy_clock.alpha(),
+                game.kitchen_input_enabled(),
+            )
+        });
+        let fill_bar_sprite = self.fill_bar.sprite_mut();
+        registry.on_update(fill_bar_sprite, |game| game.flour_pile.fill_bar.update());
+        registry.on_draw(fill_bar_sprite, |game| game.flour_pile.fill_bar.draw());
+    }
 }
 
 #[derive(Debug)]
@@ -148,13 +145,8 @@ struct AButtonIndicator {
 }
 
 impl AButtonIndicator {
-    fn new(pos: (f32, f32)) -> Self {
-        let sprite = load_sprite_at(
-            "res/a_button",
-            pos.0,
-            pos.1,
-            Some(SpriteType::AButtonIndicator as u8),
-        );
+    fn new(pos: (f32, f32), sprites: &mut SpriteOwner) -> Self {
+        let sprite = sprites.load_sprite_at("res/a_button", pos.0, pos.1);
         let graphics = Graphics::get();
         let normal_image = graphics.load_bitmap("res/a_button").unwrap();
         let pressed_image = graphics.load_bitmap("res/a_button_pressed").unwrap();
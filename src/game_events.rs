@@ -0,0 +1,71 @@
+use crate::core_elements::PastaType;
+use crate::events::EventKind;
+use crate::game_value::GameUInt;
+use alloc::vec::Vec;
+
+/// Something that happened during play that other parts of the game might want to react to
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    MoneyGained {
+        amount: GameUInt,
+        /// Where on screen the money came from, e.g. the order that was delivered
+        at: (f32, f32),
+    },
+    UpgradeBought {
+        /// Index of the item in the shop menu
+        upgrade: usize,
+        /// How many of it have now been bought
+        count: usize,
+    },
+    UpgradeDenied {
+        upgrade: usize,
+    },
+    /// An order or sale couldn't go through, e.g. there wasn't the stock for it
+    SaleDenied,
+    /// The player pressed A to knead
+    Kneaded,
+    DoughCreated,
+    /// A machine finished rolling a sheet, which became `count` pieces of `pasta`
+    SheetMade {
+        machine: usize,
+        pasta: PastaType,
+        count: usize,
+    },
+    RandomEventStarted(EventKind),
+    GoldenDoughTaken,
+    ShopOpened,
+    /// The crank was turned, raised each frame it moves. Turning it `backwards` doesn't move the
+    /// machine
+    Cranked {
+        backwards: bool,
+    },
+}
+
+/// Reacts to the events raised each frame
+pub trait GameEventSubscriber {
+    fn on_events(&mut self, events: &[GameEvent]);
+}
+
+/// Collects events as they're raised, then hands them to each subscriber in turn once per frame
+#[derive(Debug, Default)]
+pub struct GameEventBus {
+    pending: Vec<GameEvent>,
+}
+
+impl GameEventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        self.pending.push(event);
+    }
+
+    /// Subscribers see the events in the order they were raised, and are called in the order given
+    pub fn dispatch(&mut self, subscribers: &mut [&mut dyn GameEventSubscriber]) {
+        for subscriber in subscribers.iter_mut() {
+            subscriber.on_events(&self.pending);
+        }
+        self.pending.clear();
+    }
+}
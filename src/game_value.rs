@@ -1,9 +1,11 @@
 use alloc::format;
 use alloc::string::String;
 use core::fmt::{Display, Formatter};
-use core::ops::{AddAssign, Deref, DerefMut, Mul, SubAssign};
+use core::ops::{Add, AddAssign, Div, Mul, SubAssign};
+use core::sync::atomic::{AtomicU8, Ordering};
 use crankstart::Game;
 use num_bigint::BigUint;
+use num_traits::{CheckedSub, Zero};
 use serde::{Deserialize, Serialize};
 
 /// A Game Value is a value of ~inf size that has a nice iso multiple display.
@@ -11,30 +13,128 @@ use serde::{Deserialize, Serialize};
 
 const MAGNITUDES: [&'static str; 11] = ["", "k", "M", "G", "T", "P", "E", "Z", "Y", "R", "Q"];
 const MAGNITUDES_FULL: [&'static str; 11] = [
-    "", "kilo", "mega", "giga", "tera", "peta", "exa", "zetta", "yotta", "ronna", "quanto",
+    "", "kilo", "mega", "giga", "tera", "peta", "exa", "zetta", "yotta", "ronna", "quetta",
+];
+const SHORT_SCALE: [&'static str; 12] = [
+    "",
+    "thousand",
+    "million",
+    "billion",
+    "trillion",
+    "quadrillion",
+    "quintillion",
+    "sextillion",
+    "septillion",
+    "octillion",
+    "nonillion",
+    "decillion",
 ];
 
-pub trait GameValue {
-    fn to_string_hum(&self) -> String;
+/// How large numbers are displayed to the player. Formats backed by a table of names fall back to
+/// scientific notation once a value outgrows the table, so there is no upper bound.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberFormat {
+    /// 1.50k, 2.30M
+    Si,
+    /// 1.50 kilo, 2.30 mega
+    SiLong,
+    /// 1.50 thousand, 2.30 million
+    ShortScale,
+    /// 1.50e3, 2.30e6
+    Scientific,
+    /// Like scientific, but the exponent is always a multiple of 3: 1.50e3, 23.0e6
+    Engineering,
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Default, Serialize, Deserialize)]
-pub struct GameUInt {
-    value: BigUint,
-}
+static CURRENT_NUMBER_FORMAT: AtomicU8 = AtomicU8::new(NumberFormat::Si as u8);
+
+impl NumberFormat {
+    pub const ALL: [NumberFormat; 5] = [
+        NumberFormat::Si,
+        NumberFormat::SiLong,
+        NumberFormat::ShortScale,
+        NumberFormat::Scientific,
+        NumberFormat::Engineering,
+    ];
+
+    /// The format used by [GameValue::to_string_hum]
+    pub fn current() -> Self {
+        let idx = CURRENT_NUMBER_FORMAT.load(Ordering::Relaxed) as usize;
+        Self::ALL.get(idx).copied().unwrap_or(NumberFormat::Si)
+    }
+
+    pub fn set_current(self) {
+        CURRENT_NUMBER_FORMAT.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumberFormat::Si => "SI",
+            NumberFormat::SiLong => "SI (long)",
+            NumberFormat::ShortScale => "Named",
+            NumberFormat::Scientific => "Scientific",
+            NumberFormat::Engineering => "Engineering",
+        }
+    }
+
+    fn suffixes(&self) -> Option<(&'static [&'static str], &'static str)> {
+        match self {
+            NumberFormat::Si => Some((&MAGNITUDES, "")),
+            NumberFormat::SiLong => Some((&MAGNITUDES_FULL, " ")),
+            NumberFormat::ShortScale => Some((&SHORT_SCALE, " ")),
+            NumberFormat::Scientific | NumberFormat::Engineering => None,
+        }
+    }
 
-impl Deref for GameUInt {
-    type Target = BigUint;
+    /// Formats a string of decimal digits (no leading zeros) to 3 significant figures
+    pub fn format_digits(&self, digits: &str) -> String {
+        if digits.len() < 4 {
+            return digits.into();
+        }
+        let exponent = digits.len() - 1;
+        // Number of thousands groups, i.e. "1,500" is 1 and "150,000" is also 1
+        let magnitude = exponent / 3;
+        // Digits in front of the decimal point when the exponent is a multiple of 3
+        let lead = digits.len() - (magnitude * 3);
+        match self.suffixes() {
+            Some((names, separator)) if magnitude < names.len() => {
+                let mantissa = three_sig_figs(digits, lead);
+                format!("{}{}{}", mantissa, separator, names[magnitude])
+            }
+            _ if *self == NumberFormat::Engineering => {
+                let mantissa = three_sig_figs(digits, lead);
+                format!("{}e{}", mantissa, magnitude * 3)
+            }
+            _ => {
+                let mantissa = three_sig_figs(digits, 1);
+                format!("{}e{}", mantissa, exponent)
+            }
+        }
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.value
+/// Takes the first 3 digits of `digits`, with a decimal point after `lead` of them
+fn three_sig_figs(digits: &str, lead: usize) -> String {
+    let trunc = digits.split_at(3).0;
+    let (pre, post) = trunc.split_at(lead);
+    if post.is_empty() {
+        pre.into()
+    } else {
+        format!("{}.{}", pre, post)
     }
 }
 
-impl DerefMut for GameUInt {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.value
+pub trait GameValue {
+    /// Human readable string in the player's chosen [NumberFormat]
+    fn to_string_hum(&self) -> String {
+        self.to_string_with(NumberFormat::current())
     }
+    fn to_string_with(&self, format: NumberFormat) -> String;
+}
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Default, Serialize, Deserialize)]
+pub struct GameUInt {
+    value: BigUint,
 }
 
 impl AddAssign<usize> for GameUInt {
@@ -49,6 +149,17 @@ impl AddAssign<GameUInt> for GameUInt {
     }
 }
 
+impl Add for GameUInt {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            value: self.value + rhs.value,
+        }
+    }
+}
+
+/// Panics if rhs is larger than self, use [GameUInt::checked_sub] where that is possible
 impl SubAssign<GameUInt> for GameUInt {
     fn sub_assign(&mut self, rhs: GameUInt) {
         self.value -= rhs.value;
@@ -64,6 +175,26 @@ impl Mul for GameUInt {
         }
     }
 }
+
+/// Rounds down. Panics if rhs is zero, use [GameUInt::checked_div] where that is possible
+impl Div for GameUInt {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(&rhs).expect("GameUInt division by zero")
+    }
+}
+
+impl Mul<usize> for GameUInt {
+    type Output = Self;
+
+    fn mul(self, rhs: usize) -> Self::Output {
+        Self {
+            value: self.value * rhs,
+        }
+    }
+}
+
 impl<T> From<T> for GameUInt
 where
     T: Into<BigUint>,
@@ -89,29 +220,520 @@ impl GameUInt {
     pub fn one() -> Self {
         Self::from(1usize)
     }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    pub fn pow(&self, exponent: u32) -> Self {
+        Self {
+            value: self.value.pow(exponent),
+        }
+    }
+
+    /// Returns None rather than underflowing
+    pub fn checked_sub(&self, rhs: &GameUInt) -> Option<Self> {
+        self.value.checked_sub(&rhs.value).map(Self::new)
+    }
+
+    /// Integer division, rounding down. Returns None when dividing by zero
+    pub fn checked_div(&self, rhs: &GameUInt) -> Option<Self> {
+        if rhs.is_zero() {
+            None
+        } else {
+            Some(Self::new(&self.value / &rhs.value))
+        }
+    }
+
+    /// Multiply by a fractional multiplier, rounding down to a whole number
+    pub fn mul_decimal(&self, rhs: &GameDecimal) -> Self {
+        (GameDecimal::from(self.clone()) * rhs.clone()).floor()
+    }
 }
 
 impl GameValue for GameUInt {
-    fn to_string_hum(&self) -> String {
-        let mut value_s = format!("{}", self.value);
-        if value_s.len() < 4 {
-            return value_s;
-        }
-        let magnitude = value_s.len() / 3;
-        let magnitude_s = MAGNITUDES[magnitude];
-        // If it's "1,500"
-        // Magnitude is 1 = 3
-        // so decimal_places = 2 = 3 - (len - magnitude * 3) = 3 - (4 - 3) = 2
-        // If its 150,000
-        // magnitude is 1
-        // so decimal_places = 0 = 3 - (len - magnitude * 3) = 3 - (6 - 3) = 0
-        let decimal_places = 3 - (value_s.len() - (magnitude * 3));
-        let trunc = value_s.split_at(3).0;
-        let (pre, post) = trunc.split_at(decimal_places);
-        if post == "" {
-            return format!("{}{}", pre, magnitude_s);
+    fn to_string_with(&self, format: NumberFormat) -> String {
+        format.format_digits(&format!("{}", self.value))
+    }
+}
+
+/// Number of decimal places a [GameDecimal] holds
+const DECIMAL_PLACES: u32 = 4;
+
+fn decimal_scale() -> BigUint {
+    BigUint::from(10u32).pow(DECIMAL_PLACES)
+}
+
+/// A non-negative, ~inf size value with a fixed number of decimal places. Stored as a whole number
+/// mantissa that is implicitly divided by 10^[DECIMAL_PLACES]. Intended for prices and rates that
+/// need fractional multipliers, e.g. "+15% sell price" or "x1.5 auto-crank"
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Default, Serialize, Deserialize)]
+#[serde(from = "GameDecimalRepr")]
+pub struct GameDecimal {
+    mantissa: BigUint,
+}
+
+/// Saves written before [GameDecimal] existed stored whole [GameUInt]s as `{"value": ..}`, so we
+/// accept either form when loading
+#[derive(Deserialize)]
+struct GameDecimalRepr {
+    #[serde(default)]
+    mantissa: Option<BigUint>,
+    #[serde(default)]
+    value: Option<BigUint>,
+}
+
+impl From<GameDecimalRepr> for GameDecimal {
+    fn from(repr: GameDecimalRepr) -> Self {
+        match (repr.mantissa, repr.value) {
+            (Some(mantissa), _) => Self { mantissa },
+            (None, Some(value)) => GameUInt::new(value).into(),
+            (None, None) => Self::default(),
+        }
+    }
+}
+
+impl From<GameUInt> for GameDecimal {
+    fn from(value: GameUInt) -> Self {
+        Self {
+            mantissa: value.take() * decimal_scale(),
+        }
+    }
+}
+
+impl Add for GameDecimal {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            mantissa: self.mantissa + rhs.mantissa,
+        }
+    }
+}
+
+impl AddAssign for GameDecimal {
+    fn add_assign(&mut self, rhs: Self) {
+        self.mantissa += rhs.mantissa;
+    }
+}
+
+impl Mul for GameDecimal {
+    type Output = Self;
+
+    /// Rounds down to the nearest representable value
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            mantissa: (self.mantissa * rhs.mantissa) / decimal_scale(),
+        }
+    }
+}
+
+/// Rounds down. Panics if rhs is zero, use [GameDecimal::checked_div] where that is possible
+impl Div for GameDecimal {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(&rhs)
+            .expect("GameDecimal division by zero")
+    }
+}
+
+impl GameDecimal {
+    pub fn from_whole(value: usize) -> Self {
+        GameUInt::from(value).into()
+    }
+
+    /// numerator / denominator, rounded down. A zero denominator gives zero
+    pub fn from_ratio(numerator: usize, denominator: usize) -> Self {
+        if denominator == 0 {
+            return Self::default();
+        }
+        Self {
+            mantissa: (BigUint::from(numerator) * decimal_scale()) / denominator,
+        }
+    }
+
+    /// A multiplier of (100 + percent)%, i.e. `from_percent_increase(15)` is x1.15
+    pub fn from_percent_increase(percent: usize) -> Self {
+        Self::from_ratio(100 + percent, 100)
+    }
+
+    pub fn one() -> Self {
+        Self::from_whole(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    /// The whole part, with any fraction dropped
+    pub fn floor(&self) -> GameUInt {
+        GameUInt::new(&self.mantissa / decimal_scale())
+    }
+
+    /// The fractional part, less than one
+    pub fn fract(&self) -> Self {
+        Self {
+            mantissa: &self.mantissa % decimal_scale(),
+        }
+    }
+
+    /// Returns None rather than underflowing
+    pub fn checked_sub(&self, rhs: &GameDecimal) -> Option<Self> {
+        self.mantissa
+            .checked_sub(&rhs.mantissa)
+            .map(|mantissa| Self { mantissa })
+    }
+
+    /// Division, rounded down. Returns None when dividing by zero
+    pub fn checked_div(&self, rhs: &GameDecimal) -> Option<Self> {
+        if rhs.is_zero() {
+            None
+        } else {
+            Some(Self {
+                mantissa: (&self.mantissa * decimal_scale()) / &rhs.mantissa,
+            })
+        }
+    }
+
+    pub fn pow(&self, exponent: u32) -> Self {
+        (0..exponent).fold(Self::one(), |acc, _| acc * self.clone())
+    }
+}
+
+impl GameValue for GameDecimal {
+    /// Small values show two decimal places, large ones use the [NumberFormat] like [GameUInt]
+    fn to_string_with(&self, format: NumberFormat) -> String {
+        let whole = self.floor();
+        if whole >= GameUInt::from(1000usize) {
+            return whole.to_string_with(format);
+        }
+        let hundredths = &self.fract().mantissa / BigUint::from(10u32).pow(DECIMAL_PLACES - 2);
+        if hundredths.is_zero() {
+            format!("{}", whole.value)
+        } else {
+            format!("{}.{:0>2}", whole.value, hundredths)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const TABLE_FORMATS: [NumberFormat; 3] = [
+        NumberFormat::Si,
+        NumberFormat::SiLong,
+        NumberFormat::ShortScale,
+    ];
+
+    fn repeat(digit: char, count: usize) -> String {
+        core::iter::repeat_n(digit, count).collect()
+    }
+
+    /// Cheap deterministic digit strings of every length, so no test depends on a random seed
+    fn digit_strings(max_len: usize) -> Vec<String> {
+        let mut seed: u32 = 12345;
+        (1..=max_len)
+            .map(|len| {
+                (0..len)
+                    .map(|i| {
+                        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                        let digit = (seed >> 16) % 10;
+                        // No leading zeros
+                        let digit = if i == 0 { digit.max(1) } else { digit };
+                        char::from_digit(digit, 10).unwrap()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The significant digits shown, with any decimal point removed
+    fn shown_digits(formatted: &str) -> String {
+        formatted
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .filter(|c| *c != '.')
+            .collect()
+    }
+
+    #[test]
+    fn small_values_are_shown_whole() {
+        for format in NumberFormat::ALL {
+            assert_eq!(format.format_digits("0"), "0");
+            assert_eq!(format.format_digits("7"), "7");
+            assert_eq!(format.format_digits("999"), "999");
+        }
+    }
+
+    #[test]
+    fn thousands_boundary() {
+        assert_eq!(NumberFormat::Si.format_digits("1000"), "1.00k");
+        assert_eq!(NumberFormat::SiLong.format_digits("1000"), "1.00 kilo");
+        assert_eq!(
+            NumberFormat::ShortScale.format_digits("1000"),
+            "1.00 thousand"
+        );
+        assert_eq!(NumberFormat::Scientific.format_digits("1000"), "1.00e3");
+        assert_eq!(NumberFormat::Engineering.format_digits("1000"), "1.00e3");
+    }
+
+    #[test]
+    fn lengths_that_are_a_multiple_of_three() {
+        assert_eq!(NumberFormat::Si.format_digits("999999"), "999k");
+        assert_eq!(NumberFormat::SiLong.format_digits("999999"), "999 kilo");
+        assert_eq!(
+            NumberFormat::ShortScale.format_digits("999999"),
+            "999 thousand"
+        );
+        assert_eq!(NumberFormat::Scientific.format_digits("999999"), "9.99e5");
+        assert_eq!(NumberFormat::Engineering.format_digits("999999"), "999e3");
+        assert_eq!(NumberFormat::Si.format_digits("1000000"), "1.00M");
+        assert_eq!(NumberFormat::Engineering.format_digits("1000000"), "1.00e6");
+    }
+
+    #[test]
+    fn every_magnitude_boundary() {
+        for format in TABLE_FORMATS {
+            let (names, separator) = format.suffixes().unwrap();
+            for (magnitude, name) in names.iter().enumerate().skip(1) {
+                let lowest = format!("1{}", repeat('0', magnitude * 3));
+                assert_eq!(
+                    format.format_digits(&lowest),
+                    format!("1.00{}{}", separator, name)
+                );
+                let highest = repeat('9', magnitude * 3 + 3);
+                assert_eq!(
+                    format.format_digits(&highest),
+                    format!("999{}{}", separator, name)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_scientific_past_the_table() {
+        let first_unnamed = MAGNITUDES_FULL.len() * 3 + 1;
+        let digits = format!("1{}", repeat('0', first_unnamed - 1));
+        let expected = format!("1.00e{}", first_unnamed - 1);
+        assert_eq!(NumberFormat::Si.format_digits(&digits), expected);
+        assert_eq!(NumberFormat::SiLong.format_digits(&digits), expected);
+        // The short scale names go one further
+        let digits = format!("1{}", repeat('0', SHORT_SCALE.len() * 3));
+        assert_eq!(
+            NumberFormat::ShortScale.format_digits(&digits),
+            format!("1.00e{}", SHORT_SCALE.len() * 3)
+        );
+        // Nothing panics however large the value gets
+        for format in NumberFormat::ALL {
+            let expected = match format {
+                NumberFormat::Engineering => "11.1e198",
+                _ => "1.11e199",
+            };
+            assert_eq!(format.format_digits(&repeat('1', 200)), expected);
+        }
+    }
+
+    #[test]
+    fn every_format_shows_the_same_lead_digits() {
+        for digits in digit_strings(80) {
+            let expected: String = digits.chars().take(3).collect();
+            for format in NumberFormat::ALL {
+                let formatted = format.format_digits(&digits);
+                assert_eq!(
+                    shown_digits(&formatted),
+                    expected,
+                    "{:?} formatted {} as {}",
+                    format,
+                    digits,
+                    formatted
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exponents_match_the_number_of_digits() {
+        for digits in digit_strings(80).into_iter().skip(3) {
+            let scientific = NumberFormat::Scientific.format_digits(&digits);
+            let exponent: usize = scientific.split('e').nth(1).unwrap().parse().unwrap();
+            assert_eq!(exponent, digits.len() - 1);
+            let engineering = NumberFormat::Engineering.format_digits(&digits);
+            let exponent: usize = engineering.split('e').nth(1).unwrap().parse().unwrap();
+            assert_eq!(exponent % 3, 0);
+            assert_eq!(exponent / 3, (digits.len() - 1) / 3);
+        }
+    }
+
+    /// Largest power of ten swept, well past the end of every suffix table
+    const SWEEP_POWERS: u32 = 150;
+
+    /// Values across every magnitude up to 10^[SWEEP_POWERS], in increasing order
+    fn sweep_values() -> Vec<GameUInt> {
+        let ten = GameUInt::from(10usize);
+        let mut values: Vec<GameUInt> = (0..=SWEEP_POWERS)
+            .flat_map(|power| {
+                let scale = ten.pow(power);
+                [1usize, 101, 15, 2, 45, 5, 999, 9995]
+                    .into_iter()
+                    .map(move |lead| GameUInt::from(lead) * scale.clone())
+            })
+            .collect();
+        // The largest value with each number of digits
+        values.extend(
+            (1..=SWEEP_POWERS).map(|power| ten.pow(power).checked_sub(&GameUInt::one()).unwrap()),
+        );
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    /// Three significant figures and a decimal point, then the longest suffix the format has or
+    /// an exponent, whichever is wider
+    fn width_limit(format: NumberFormat, exponent: usize) -> usize {
+        let exponent_width = 1 + format!("{}", exponent).len();
+        let suffix_width = match format.suffixes() {
+            Some((names, separator)) => names
+                .iter()
+                .map(|name| separator.len() + name.len())
+                .max()
+                .unwrap(),
+            None => 0,
+        };
+        4 + suffix_width.max(exponent_width)
+    }
+
+    /// Power of ten of the leading digit and the first three significant digits, read back from a
+    /// formatted value. These order formatted values the same way as the values themselves
+    fn read_back(format: NumberFormat, formatted: &str) -> (usize, usize) {
+        let number: String = formatted
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let rest = &formatted[number.len()..];
+        let suffix_exponent = if rest.is_empty() {
+            0
+        } else if let Some(exponent) = rest.strip_prefix('e') {
+            exponent.parse().unwrap()
         } else {
-            return format!("{}.{}{}", pre, post, magnitude_s);
+            let (names, separator) = format.suffixes().unwrap();
+            let idx = names
+                .iter()
+                .position(|name| format!("{}{}", separator, name) == rest)
+                .unwrap_or_else(|| panic!("Unknown suffix in {}", formatted));
+            idx * 3
+        };
+        let lead = number.split('.').next().unwrap().len();
+        let digits: String = number.chars().filter(|c| *c != '.').collect();
+        let sig_figs = format!("{:0<3}", digits).parse().unwrap();
+        (suffix_exponent + lead - 1, sig_figs)
+    }
+
+    #[test]
+    fn sweep_stays_within_the_width_limit() {
+        for value in sweep_values() {
+            let exponent = format!("{}", value.value).len() - 1;
+            for format in NumberFormat::ALL {
+                let formatted = value.to_string_with(format);
+                assert!(
+                    formatted.len() <= width_limit(format, exponent),
+                    "{:?} formatted 10^{} as {}, which is too wide",
+                    format,
+                    exponent,
+                    formatted
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sweep_is_monotonic_and_keeps_the_magnitude() {
+        let values = sweep_values();
+        for format in NumberFormat::ALL {
+            let mut previous = (0, 0);
+            for value in values.iter() {
+                let formatted = value.to_string_with(format);
+                let shown = read_back(format, &formatted);
+                let exponent = format!("{}", value.value).len() - 1;
+                assert_eq!(shown.0, exponent, "{:?} formatted {}", format, formatted);
+                assert!(
+                    shown >= previous,
+                    "{:?} formatted {} below a smaller value",
+                    format,
+                    formatted
+                );
+                previous = shown;
+            }
+        }
+    }
+
+    #[test]
+    fn game_uint_uses_the_given_format() {
+        let value = GameUInt::from(1_500_000usize);
+        assert_eq!(value.to_string_with(NumberFormat::Si), "1.50M");
+        assert_eq!(
+            value.to_string_with(NumberFormat::ShortScale),
+            "1.50 million"
+        );
+        assert_eq!(value.to_string_with(NumberFormat::Scientific), "1.50e6");
+    }
+
+    #[test]
+    fn dividing_by_zero_gives_none() {
+        assert_eq!(
+            GameUInt::from(5usize).checked_div(&GameUInt::default()),
+            None
+        );
+        assert_eq!(GameUInt::default().checked_div(&GameUInt::default()), None);
+        assert_eq!(
+            GameDecimal::from_whole(5).checked_div(&GameDecimal::default()),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_panics_on_zero() {
+        let _ = GameUInt::from(5usize) / GameUInt::default();
+    }
+
+    #[test]
+    fn game_uint_division_rounds_down() {
+        let div = |a: usize, b: usize| GameUInt::from(a) / GameUInt::from(b);
+        assert_eq!(div(6, 3), GameUInt::from(2usize));
+        assert_eq!(div(7, 2), GameUInt::from(3usize));
+        assert_eq!(div(2, 3), GameUInt::default());
+        let big = GameUInt::from(10usize).pow(40);
+        assert_eq!(big.clone() / big, GameUInt::one());
+    }
+
+    #[test]
+    fn game_decimal_division_rounds_down() {
+        let div = |a: usize, b: usize| GameDecimal::from_whole(a) / GameDecimal::from_whole(b);
+        assert_eq!(div(3, 2), GameDecimal::from_ratio(3, 2));
+        // 0.3333... and 0.6666... are cut off at the last decimal place, not rounded up
+        assert_eq!(div(1, 3), GameDecimal::from_ratio(3333, 10000));
+        assert_eq!(div(2, 3), GameDecimal::from_ratio(6666, 10000));
+        assert_eq!(
+            GameDecimal::from_ratio(3, 4) / GameDecimal::from_ratio(1, 4),
+            GameDecimal::from_whole(3)
+        );
+    }
+
+    #[test]
+    fn division_undoes_multiplication() {
+        for a in [0usize, 1, 7, 150, 99_999] {
+            for b in [1usize, 2, 3, 1000] {
+                let product = GameDecimal::from_whole(a) * GameDecimal::from_whole(b);
+                assert_eq!(
+                    product / GameDecimal::from_whole(b),
+                    GameDecimal::from_whole(a)
+                );
+                let product = GameUInt::from(a) * GameUInt::from(b);
+                assert_eq!(product / GameUInt::from(b), GameUInt::from(a));
+            }
         }
     }
 }
@@ -1,6 +1,8 @@
 use crankstart::graphics::Graphics;
 use crankstart::sprite::{Sprite, SpriteManager};
+use crankstart::system::System;
 use crankstart_sys::LCDBitmapFlip;
+use serde::{Deserialize, Serialize};
 
 pub fn wrap(val: f32, min: f32, max: f32) -> f32 {
     // Note: if val is bigger than min-max overshot, it'll not be returned in that range
@@ -30,7 +32,7 @@ pub fn wrap_with_info(val: f32, min: f32, max: f32) -> (f32, WrapResult) {
     }
 }
 
-pub fn load_sprite_at(filename: &str, x: f32, y: f32, tag: Option<u8>) -> Sprite {
+pub fn load_sprite_at(filename: &str, x: f32, y: f32) -> Sprite {
     let sprite_manager = SpriteManager::get_mut();
     let mut sprite = sprite_manager.new_sprite().unwrap();
     let image = Graphics::get().load_bitmap(filename).unwrap();
@@ -38,9 +40,61 @@ pub fn load_sprite_at(filename: &str, x: f32, y: f32, tag: Option<u8>) -> Sprite
         .set_image(image, LCDBitmapFlip::kBitmapUnflipped)
         .unwrap();
     sprite.move_to(x, y).unwrap();
-    if let Some(tag) = tag {
-        sprite.set_tag(tag).unwrap();
-    }
     sprite_manager.add_sprite(&sprite).unwrap();
     sprite
 }
+
+/// Wall clock time in seconds. Unlike elapsed time this carries on while the game isn't running, so
+/// it's what offline progress is measured with
+pub fn seconds_since_epoch() -> usize {
+    System::get()
+        .get_seconds_since_epoch()
+        .map(|(seconds, _millis)| seconds)
+        .unwrap_or(0)
+}
+
+/// Small seedable xorshift RNG. Not remotely cryptographic, but cheap, deterministic for a given
+/// seed and serialisable so anything random can be saved and resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        // xorshift gets stuck on zero
+        Self {
+            state: if seed == 0 { 0x9E37_79B9 } else { seed },
+        }
+    }
+
+    /// Seeded from the clock, for when we don't care about reproducing the sequence
+    pub fn from_time() -> Self {
+        let millis = System::get().get_current_time_milliseconds().unwrap_or(0);
+        Self::new(millis as u32)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// In the range [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// In the range [min, max)
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + self.next_f32() * (max - min)
+    }
+
+    /// In the range [min, max]
+    pub fn range_usize(&mut self, min: usize, max: usize) -> usize {
+        min + (self.next_u32() as usize % (max - min + 1))
+    }
+}
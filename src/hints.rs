@@ -0,0 +1,272 @@
+use crate::core_elements::CoreState;
+use crate::game_events::{GameEvent, GameEventSubscriber};
+use crate::game_value::GameUInt;
+use crate::sprite_owner::SpriteOwner;
+use alloc::vec::Vec;
+use crankstart::graphics::LCDColor;
+use crankstart::log_to_console;
+use crankstart::sprite::{Sprite, TextSprite};
+use crankstart::system::System;
+use crankstart_sys::{LCDSolidColor, PDButtons};
+use serde::{Deserialize, Serialize};
+
+/// Something the player seems to have missed, noticed from what's going on in the kitchen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hint {
+    /// Dough balls are stacking up and nothing is rolling them
+    DoughPilingUp,
+    /// There's been enough money for an upgrade for a while without the shop being opened
+    MoneyIdle,
+    /// The crank is being turned the way that doesn't do anything
+    CrankingBackwards,
+    /// A is being held down, which doesn't knead
+    HoldingA,
+}
+
+impl Hint {
+    pub const ALL: [Hint; 4] = [
+        Hint::DoughPilingUp,
+        Hint::MoneyIdle,
+        Hint::CrankingBackwards,
+        Hint::HoldingA,
+    ];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|hint| *hint == self).unwrap()
+    }
+
+    fn lines(self) -> (&'static str, &'static str) {
+        match self {
+            Hint::DoughPilingUp => ("Dough is piling up!", "Turn the crank to roll it"),
+            Hint::MoneyIdle => ("You can afford an upgrade", "Press Left to open the shop"),
+            Hint::CrankingBackwards => ("That's the wrong way!", "Turn the crank the other way"),
+            Hint::HoldingA => ("Holding A doesn't knead", "Tap it instead"),
+        }
+    }
+
+    /// Seconds the situation has to carry on for before the hint is worth showing
+    fn delay(self) -> f32 {
+        match self {
+            Hint::DoughPilingUp => 20.0,
+            Hint::MoneyIdle => 30.0,
+            Hint::CrankingBackwards => 0.0,
+            Hint::HoldingA => 2.0,
+        }
+    }
+}
+
+/// The saved part of [Hints]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HintsState {
+    /// Hints the player has acted on, or has been shown enough times, which don't come back
+    dismissed: Vec<Hint>,
+}
+
+/// Watches for mechanics the player seems to have missed and points them out in a bubble. Hints
+/// don't take any input, and go away on their own once the player does what they suggest
+#[derive(Debug)]
+pub struct Hints {
+    state: HintsState,
+    enabled: bool,
+    _sprites: SpriteOwner,
+    bubble: Sprite,
+    first_line: TextSprite,
+    second_line: TextSprite,
+    /// Elapsed time each hint's situation started at, in the order of [Hint::ALL]
+    noticed_since: [Option<f32>; Hint::ALL.len()],
+    /// Times each hint has been shown this session without being acted on
+    times_shown: [usize; Hint::ALL.len()],
+    /// The hint on screen and the elapsed time it was shown at
+    showing: Option<(Hint, f32)>,
+    /// No new hints are shown before this elapsed time
+    quiet_until: f32,
+    last_dough_balls: GameUInt,
+    /// Elapsed times of the backwards turns since the crank last went forwards, dropped once
+    /// they're older than [Hints::BACKWARDS_WINDOW_SECS]
+    backwards_turns: Vec<f32>,
+}
+
+impl Hints {
+    const Z_INDEX: i16 = 40;
+    const BUBBLE_POS: (f32, f32) = (200.0, 168.0);
+    const SHOW_SECS: f32 = 6.0;
+    /// Seconds between one hint going away and the next being shown
+    const GAP_SECS: f32 = 60.0;
+    /// A hint that's been ignored this many times isn't shown again
+    const MAX_SHOWINGS: usize = 3;
+    const DOUGH_PILE_SIZE: usize = 10;
+    /// Frames of backwards cranking within the window before it's worth saying anything
+    const BACKWARDS_TURNS: usize = 30;
+    const BACKWARDS_WINDOW_SECS: f32 = 3.0;
+
+    pub fn new(state: HintsState, enabled: bool) -> Self {
+        let mut sprites = SpriteOwner::new();
+        let (x, y) = Self::BUBBLE_POS;
+        let mut bubble = sprites.load_sprite_at("res/callout", x, y);
+        bubble.set_z_index(Self::Z_INDEX).unwrap();
+        let mut first_line =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        first_line.get_sprite_mut().move_to(x, y - 9.0).unwrap();
+        first_line
+            .get_sprite_mut()
+            .set_z_index(Self::Z_INDEX + 1)
+            .unwrap();
+        let mut second_line =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        second_line.get_sprite_mut().move_to(x, y + 10.0).unwrap();
+        second_line
+            .get_sprite_mut()
+            .set_z_index(Self::Z_INDEX + 1)
+            .unwrap();
+        let mut s = Self {
+            state,
+            enabled,
+            _sprites: sprites,
+            bubble,
+            first_line,
+            second_line,
+            noticed_since: [None; Hint::ALL.len()],
+            times_shown: [0; Hint::ALL.len()],
+            showing: None,
+            quiet_until: 0.0,
+            last_dough_balls: GameUInt::default(),
+            backwards_turns: Vec::new(),
+        };
+        s.set_visible(false);
+        s
+    }
+
+    pub fn to_saved(&self) -> HintsState {
+        self.state.clone()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn is_dismissed(&self, hint: Hint) -> bool {
+        self.state.dismissed.contains(&hint)
+    }
+
+    fn dismiss(&mut self, hint: Hint) {
+        if !self.is_dismissed(hint) {
+            log_to_console!("Dismissing hint {:?}", hint);
+            self.state.dismissed.push(hint);
+        }
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.bubble.set_visible(visible).unwrap();
+        self.first_line
+            .get_sprite_mut()
+            .set_visible(visible)
+            .unwrap();
+        self.second_line
+            .get_sprite_mut()
+            .set_visible(visible)
+            .unwrap();
+    }
+
+    fn show(&mut self, hint: Hint, now: f32) {
+        let (first, second) = hint.lines();
+        self.first_line.update_text(first).unwrap();
+        self.second_line.update_text(second).unwrap();
+        self.set_visible(true);
+        self.showing = Some((hint, now));
+    }
+
+    fn hide(&mut self, now: f32) {
+        self.set_visible(false);
+        self.showing = None;
+        self.quiet_until = now + Self::GAP_SECS;
+    }
+
+    /// Whether the situation each hint is about is going on right now
+    fn is_happening(
+        &self,
+        hint: Hint,
+        state: &CoreState,
+        cheapest_upgrade: Option<&GameUInt>,
+        kitchen_in_view: bool,
+    ) -> bool {
+        match hint {
+            Hint::DoughPilingUp => {
+                state.dough_balls >= GameUInt::from(Self::DOUGH_PILE_SIZE)
+                    && state.dough_balls >= self.last_dough_balls
+            }
+            Hint::MoneyIdle => {
+                kitchen_in_view && cheapest_upgrade.is_some_and(|cost| state.money >= *cost)
+            }
+            Hint::CrankingBackwards => self.backwards_turns.len() >= Self::BACKWARDS_TURNS,
+            Hint::HoldingA => {
+                let (held, _, _) = System::get().get_button_state().unwrap();
+                kitchen_in_view && (held & PDButtons::kButtonA).0 != 0
+            }
+        }
+    }
+
+    /// `kitchen_in_view` is false while a panel (e.g. the shop) covers the kitchen, and `blocked`
+    /// holds hints back without forgetting what's been noticed, e.g. while the tutorial is running
+    pub fn update(
+        &mut self,
+        state: &CoreState,
+        cheapest_upgrade: Option<&GameUInt>,
+        kitchen_in_view: bool,
+        blocked: bool,
+    ) {
+        let now = System::get().get_elapsed_time().unwrap_or(0.0);
+        self.backwards_turns
+            .retain(|at| now - at < Self::BACKWARDS_WINDOW_SECS);
+        for hint in Hint::ALL {
+            let happening = self.is_happening(hint, state, cheapest_upgrade, kitchen_in_view);
+            let since = &mut self.noticed_since[hint.index()];
+            if !happening {
+                *since = None;
+            } else if since.is_none() {
+                *since = Some(now);
+            }
+        }
+        self.last_dough_balls = state.dough_balls.clone();
+
+        if let Some((hint, shown_at)) = self.showing {
+            if self.noticed_since[hint.index()].is_none() {
+                // The player did what it said
+                self.dismiss(hint);
+                self.hide(now);
+            } else if !self.enabled || blocked {
+                self.hide(now);
+            } else if now - shown_at >= Self::SHOW_SECS {
+                self.times_shown[hint.index()] += 1;
+                if self.times_shown[hint.index()] >= Self::MAX_SHOWINGS {
+                    self.dismiss(hint);
+                }
+                self.hide(now);
+            }
+        } else if self.enabled && !blocked && now >= self.quiet_until {
+            let due = Hint::ALL.into_iter().find(|hint| {
+                !self.is_dismissed(*hint)
+                    && self.noticed_since[hint.index()]
+                        .is_some_and(|since| now - since >= hint.delay())
+            });
+            if let Some(hint) = due {
+                self.show(hint, now);
+            }
+        }
+    }
+}
+
+impl GameEventSubscriber for Hints {
+    fn on_events(&mut self, events: &[GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::Cranked { backwards: true } => {
+                    let now = System::get().get_elapsed_time().unwrap_or(0.0);
+                    self.backwards_turns.push(now);
+                }
+                // It's going the right way now
+                GameEvent::Cranked { backwards: false } => self.backwards_turns.clear(),
+                _ => {}
+            }
+        }
+    }
+}
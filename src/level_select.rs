@@ -1,41 +1,59 @@
 use crate::core_elements::{CoreParameters, CoreState, VisibilityState};
 use crate::game_value::GameValue;
+use crate::music::{Music, MusicTrack};
 use crate::save;
+use crate::scene::{Scene, SceneTransition};
+use crate::settings::Settings;
+use crate::sprite_owner::SpriteOwner;
+use crate::GameState;
+use alloc::boxed::Box;
 use alloc::format;
+use alloc::rc::Rc;
 use alloc::string::ToString;
 use alloc::vec::Vec;
+use anyhow::Error;
+use core::cell::RefCell;
 use core::ops::SubAssign;
-use crankstart::graphics::{Bitmap, Graphics};
+use crankstart::graphics::{Bitmap, Graphics, LCDColor};
 use crankstart::log_to_console;
 use crankstart::sprite::{Sprite, TextSprite};
 use crankstart::system::System;
-use crankstart_sys::{LCDBitmapFlip, PDButtons};
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons};
 
 #[derive(Debug)]
 pub struct LevelSelect {
     save_files: Vec<Option<(CoreState, CoreParameters)>>,
+    _sprites: SpriteOwner,
     menu_items: Vec<MenuItem>,
     selected_index: i32,
     pressed_index: Option<i32>,
+    /// Shared with the game once a level is picked, so the music carries on across the switch
+    music: Rc<RefCell<Music>>,
+    /// Loaded once at startup and handed on to the game
+    settings: Settings,
 }
 
 impl LevelSelect {
-    pub fn new() -> Self {
+    pub fn new(music: Rc<RefCell<Music>>, settings: Settings) -> Self {
         let save_files = save::load_all_partial();
 
+        let mut sprites = SpriteOwner::new();
         let mut menu_items = Vec::new();
         let mut y = 40.0;
         for (idx, save) in save_files.iter().enumerate() {
-            let item = MenuItem::new(y, idx, save);
+            let item = MenuItem::new(y, idx, save, &mut sprites);
             y += 80.0;
             menu_items.push(item);
         }
         menu_items[0].set_selected(true);
         Self {
             save_files,
+            _sprites: sprites,
             menu_items,
             selected_index: 0,
             pressed_index: None,
+            music,
+            settings,
         }
     }
 
@@ -48,7 +66,8 @@ impl LevelSelect {
     }
 
     /// Returns None until a level is selected, at which point it returns Some(level_number)
-    pub fn update(&mut self) -> Option<usize> {
+    fn update_selection(&mut self) -> Option<usize> {
+        self.music.borrow_mut().update(false);
         let (_, pressed, released) = System::get().get_button_state().unwrap();
         if (pressed & PDButtons::kButtonUp).0 != 0 {
             self.change_selected_item(-1);
@@ -73,6 +92,27 @@ impl LevelSelect {
     }
 }
 
+impl Scene for LevelSelect {
+    fn enter(&mut self) {
+        self.music.borrow_mut().play(MusicTrack::LevelSelect);
+    }
+
+    fn update(&mut self) -> Result<SceneTransition, Error> {
+        match self.update_selection() {
+            Some(selected_level_idx) => {
+                log_to_console!("El levelo selecte! {}", selected_level_idx);
+                let game_state = GameState::new(
+                    selected_level_idx,
+                    self.music.clone(),
+                    self.settings.clone(),
+                )?;
+                Ok(SceneTransition::Replace(Box::new(game_state)))
+            }
+            None => Ok(SceneTransition::None),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct MenuItem {
     sprite: Sprite,
@@ -86,19 +126,21 @@ pub struct MenuItem {
 impl MenuItem {
     const NAME_TEXT_OFFSET: f32 = -18.0;
     const COST_TEXT_OFFSET: f32 = 18.0;
-    pub fn new(y: f32, idx: usize, data: &Option<(CoreState, CoreParameters)>) -> Self {
+    pub fn new(
+        y: f32,
+        idx: usize,
+        data: &Option<(CoreState, CoreParameters)>,
+        sprites: &mut SpriteOwner,
+    ) -> Self {
         let x = 200.0;
-        let mut sprite = crate::helpers::load_sprite_at("res/menu_item_background0", x, y, None);
+        let mut sprite = sprites.load_sprite_at("res/menu_item_background0", x, y);
         sprite.set_z_index(10).unwrap();
         let name_text_str = match data {
             Some((_, _)) => format!("Level {}", idx + 1),
             None => "New Game".to_string(),
         };
-        let mut name_text = TextSprite::new(
-            name_text_str,
-            crankstart::graphics::LCDColor::Solid(crankstart_sys::LCDSolidColor::kColorWhite),
-        )
-        .unwrap();
+        let mut name_text =
+            sprites.new_text_sprite(&name_text_str, LCDColor::Solid(LCDSolidColor::kColorWhite));
         name_text
             .get_sprite_mut()
             .move_to(x, y + Self::NAME_TEXT_OFFSET)
@@ -106,11 +148,8 @@ impl MenuItem {
         name_text.get_sprite_mut().set_z_index(11).unwrap();
         // TODO: Populate desc_text_str
         let desc_text_str = "Desc";
-        let mut desc_text = TextSprite::new(
-            desc_text_str,
-            crankstart::graphics::LCDColor::Solid(crankstart_sys::LCDSolidColor::kColorWhite),
-        )
-        .unwrap();
+        let mut desc_text =
+            sprites.new_text_sprite(desc_text_str, LCDColor::Solid(LCDSolidColor::kColorWhite));
         desc_text.get_sprite_mut().move_to(x, y).unwrap();
         desc_text.get_sprite_mut().set_z_index(11).unwrap();
 
@@ -4,7 +4,7 @@ extern crate alloc;
 use alloc::rc::Rc;
 use alloc::vec::Vec;
 use alloc::{format, vec};
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::fmt::Debug;
 use crankstart::log_to_console;
 use crankstart::sprite::Sprite;
@@ -23,58 +23,52 @@ use {
 mod audio_events;
 mod bottom_bar;
 mod core_elements;
+mod crank_sound;
 mod dough_store;
+mod effects;
+mod events;
 mod fill_bar;
 mod flour_pile;
+mod game_events;
 mod game_value;
 mod helpers;
-mod info_overlay;
+mod hints;
 mod level_select;
 mod machine;
 mod menu;
+mod music;
+mod orders;
+mod production;
 mod save;
+mod scene;
+mod settings;
+mod sprite_owner;
+mod sprite_registry;
+mod tutorial;
+mod tween;
+mod workers;
 
-use crate::audio_events::{AudioEventChannel, SoundStore};
+use crate::audio_events::SoundStore;
 use crate::bottom_bar::BottomBar;
-use crate::core_elements::{CoreParameters, CoreState, Timer};
+use crate::core_elements::{CoreParameters, CoreState, FixedStep, Timer};
+use crate::effects::Effects;
+use crate::events::{EventsState, RandomEvents};
 use crate::flour_pile::FlourPile;
+use crate::game_events::GameEventBus;
 use crate::game_value::GameUInt;
-use crate::info_overlay::InfoOverlay;
+use crate::hints::{Hints, HintsState};
 use crate::level_select::LevelSelect;
 use crate::menu::Menu;
+use crate::music::{Music, MusicTrack};
+use crate::orders::{OrderBoard, OrdersState};
+use crate::production::ProductionChain;
+use crate::save::WorkInProgress;
+use crate::scene::{Scene, SceneStack, SceneTransition};
+use crate::settings::{Settings, SettingsScene, SettingsScreen};
+use crate::sprite_registry::SpriteRegistry;
+use crate::tutorial::{Target, Tutorial, TutorialStep};
 use machine::PastaMachineState;
 
-#[repr(u8)]
-#[derive(Debug, Copy, Clone)]
-enum SpriteType {
-    MachineCrank,
-    MachineBody,
-    MachineDough,
-    DoughStoreDough,
-    BottomBar,
-    FillBar,
-    FlourPile,
-    AButtonIndicator,
-    Menu,
-}
-
-impl From<u8> for SpriteType {
-    fn from(val: u8) -> Self {
-        match val {
-            0 => Self::MachineCrank,
-            1 => Self::MachineBody,
-            2 => Self::MachineDough,
-            3 => Self::DoughStoreDough,
-            4 => Self::BottomBar,
-            5 => Self::FillBar,
-            6 => Self::FlourPile,
-            7 => Self::AButtonIndicator,
-            8 => Self::Menu,
-            _ => panic!("Unknown sprite type {}", val),
-        }
-    }
-}
-
 #[derive(Debug)]
 struct GameState {
     parameters: CoreParameters,
@@ -83,11 +77,23 @@ struct GameState {
     bottom_bar: BottomBar,
     flour_pile: FlourPile,
     menu: Menu,
+    random_events: RandomEvents,
+    order_board: OrderBoard,
+    production_chain: ProductionChain,
     save_timer: Timer,
     sound_store: SoundStore,
-    audio_event_channel: AudioEventChannel,
+    music: Rc<RefCell<Music>>,
+    effects: Effects,
+    game_events: GameEventBus,
+    /// Steps the economy at a constant rate, whatever the display refresh rate
+    economy_clock: FixedStep,
+    sprite_registry: SpriteRegistry<GameState>,
     save_index: usize,
-    info_overlay: Rc<RefCell<InfoOverlay>>,
+    tutorial: Tutorial,
+    hints: Hints,
+    settings_screen: Rc<RefCell<SettingsScreen>>,
+    /// Set from the system menu, as its callbacks can't push scenes themselves
+    settings_requested: Rc<Cell<bool>>,
     system_menu_items: SystemMenuItems,
 }
 
@@ -100,86 +106,224 @@ impl Debug for SystemMenuItems {
 }
 
 impl GameState {
-    pub fn new(save_index: usize) -> Result<Self, Error> {
-        crankstart::display::Display::get().set_refresh_rate(20.0)?;
-        let (state, parameters, menu_counts, is_new_game) = match save::load_state(save_index) {
-            Ok((state, parameters, menu_counts)) => {
+    /// `settings` have already been loaded and applied by [PastaGame]
+    pub fn new(
+        save_index: usize,
+        music: Rc<RefCell<Music>>,
+        settings: Settings,
+    ) -> Result<Self, Error> {
+        let (
+            state,
+            parameters,
+            menu_counts,
+            events,
+            orders,
+            work_in_progress,
+            tutorial_step,
+            hints_state,
+        ) = match save::load_state(save_index) {
+            Ok(mut save_state) => {
                 System::log_to_console("Loaded save");
-                (state, parameters, Some(menu_counts), false)
+                if let Some(offline_secs) = save_state.offline_secs() {
+                    log_to_console!("Catching up on {}s offline", offline_secs);
+                    save_state
+                        .state
+                        .fast_forward(&save_state.parameters, offline_secs as f32);
+                }
+                (
+                    save_state.state,
+                    save_state.parameters,
+                    Some(save_state.menu_counts),
+                    save_state.events,
+                    save_state.orders,
+                    save_state.work_in_progress,
+                    save_state.tutorial,
+                    save_state.hints,
+                )
             }
             Err(e) => {
                 log_to_console!("Failed to load save, using defaults. Error: {:?}", e);
-                (CoreState::default(), CoreParameters::default(), None, true)
+                (
+                    CoreState::default(),
+                    CoreParameters::default(),
+                    None,
+                    EventsState::default(),
+                    OrdersState::default(),
+                    WorkInProgress::default(),
+                    if settings.show_tips {
+                        TutorialStep::Knead
+                    } else {
+                        TutorialStep::Done
+                    },
+                    HintsState::default(),
+                )
             }
         };
-        let info_overlay = Rc::new(RefCell::new(InfoOverlay::new(is_new_game)));
-        let info_menu_item = {
-            let info_overlay_clone = info_overlay.clone();
+        let tutorial = Tutorial::new(tutorial_step);
+        let hints = Hints::new(hints_state, settings.show_tips);
+        let tutorial_menu_item = {
+            let restart_request = tutorial.restart_request();
             System::get().add_menu_item(
-                "Show Help",
+                "Tutorial",
                 Box::new(move || {
-                    info_overlay_clone.borrow_mut().show();
+                    restart_request.set(true);
                 }),
             )?
         };
-        let system_menu_items = SystemMenuItems(vec![info_menu_item]);
+        let mut pasta_machine = PastaMachineState::new(work_in_progress.machines);
+        pasta_machine.set_crank_direction(settings.crank_direction);
+        pasta_machine.set_sound_volume(settings.sfx_volume());
+        let mut flour_pile = FlourPile::new((80.0, 80.0));
+        flour_pile.set_fill_pct(work_in_progress.knead_fill_pct);
+        let next_machine_item = {
+            let switch_request = pasta_machine.switch_request();
+            System::get().add_menu_item(
+                "Next Machine",
+                Box::new(move || {
+                    switch_request.set(true);
+                }),
+            )?
+        };
+        let mut sound_store = SoundStore::new();
+        sound_store.set_volume(settings.sfx_volume());
+        music.borrow_mut().set_volume(settings.music_volume());
+        let settings_screen = Rc::new(RefCell::new(SettingsScreen::new(settings)));
+        let settings_requested = Rc::new(Cell::new(false));
+        let settings_menu_item = {
+            let settings_requested_clone = settings_requested.clone();
+            System::get().add_menu_item(
+                "Settings",
+                Box::new(move || {
+                    settings_requested_clone.set(true);
+                }),
+            )?
+        };
+        let system_menu_items = SystemMenuItems(vec![
+            tutorial_menu_item,
+            next_machine_item,
+            settings_menu_item,
+        ]);
         let mut menu = Menu::new();
         if let Some(counts) = menu_counts {
             menu.init_counts(&counts);
         }
-        let sound_store = SoundStore::new()?;
+        let mut bottom_bar = BottomBar::new();
+        let mut sprite_registry = SpriteRegistry::new();
+        pasta_machine.register_sprites(&mut sprite_registry);
+        flour_pile.register_sprites(&mut sprite_registry);
+        bottom_bar.register_sprites(&mut sprite_registry);
+        menu.register_sprites(&mut sprite_registry);
         Ok(Self {
             parameters,
             state,
-            pasta_machine: PastaMachineState::new(),
-            bottom_bar: BottomBar::new(),
-            flour_pile: FlourPile::new((80.0, 80.0)),
+            pasta_machine,
+            bottom_bar,
+            flour_pile,
             menu,
+            random_events: RandomEvents::new(events),
+            order_board: OrderBoard::new(orders),
+            production_chain: ProductionChain::new(),
             save_timer: Timer::new(5.0),
             sound_store,
-            audio_event_channel: AudioEventChannel::new(),
+            music,
+            effects: Effects::new(),
+            game_events: GameEventBus::new(),
+            economy_clock: FixedStep::new(Self::ECONOMY_STEP_SECS, Self::MAX_CATCH_UP_STEPS),
+            sprite_registry,
             save_index,
-            info_overlay,
+            tutorial,
+            hints,
+            settings_screen,
+            settings_requested,
             system_menu_items,
         })
     }
 }
 
-#[derive(Debug)]
-enum GameMode {
-    LevelSelect(LevelSelect),
-    Game(GameState),
+impl GameState {
+    const ECONOMY_STEP_SECS: f32 = 0.1;
+    /// A second of catch up at most, anything longer than that is skipped
+    const MAX_CATCH_UP_STEPS: usize = 10;
+
+    /// Advances everything that runs on its own (timers, workers, motors and so on) by `dt`
+    fn step_economy(&mut self, dt: f32) {
+        self.random_events
+            .step(dt, &mut self.state, &self.parameters, &mut self.game_events);
+        self.order_board.step(dt, &self.parameters);
+        self.production_chain
+            .step(dt, &mut self.state, &self.parameters);
+        self.flour_pile.step(
+            dt,
+            &mut self.state,
+            &self.parameters,
+            &self.random_events,
+            &mut self.game_events,
+        );
+        self.pasta_machine
+            .step(dt, &mut self.state, &self.parameters, &mut self.game_events);
+    }
+
+    /// False while a panel covering the kitchen (the shop or the order board) takes button presses
+    fn kitchen_input_enabled(&self) -> bool {
+        !self.menu.is_visible() && !self.order_board.is_visible()
+    }
+
+    /// Where the sprite the tutorial is pointing at currently is
+    fn target_bounds(&self, target: Target) -> PDRect {
+        match target {
+            Target::AButton => self.flour_pile.button_bounds(),
+            Target::FillBar => self.flour_pile.fill_bar_bounds(),
+            Target::Crank => self.pasta_machine.crank_bounds(),
+            Target::OrderBoard => self.order_board.bounds(),
+            Target::MenuIndicator => self.bottom_bar.menu_indicator_bounds(),
+            Target::SelectedShopItem => self.menu.selected_item_bounds(),
+        }
+    }
+
+    /// Passes on any changes made on the settings screen
+    fn apply_settings_changes(&mut self) {
+        let mut settings_screen = self.settings_screen.borrow_mut();
+        if let Some(settings) = settings_screen.take_changed() {
+            self.sound_store.set_volume(settings.sfx_volume());
+            self.music.borrow_mut().set_volume(settings.music_volume());
+            self.pasta_machine
+                .set_crank_direction(settings.crank_direction);
+            self.pasta_machine.set_sound_volume(settings.sfx_volume());
+            if !settings.show_tips {
+                self.tutorial.skip();
+            }
+            self.hints.set_enabled(settings.show_tips);
+        }
+    }
+
+    /// Picks the track for what's going on, bringing in more layers as the kitchen gets busier
+    fn update_music(&mut self) {
+        let mut music = self.music.borrow_mut();
+        if self.random_events.is_running() {
+            music.play(MusicTrack::Event);
+        } else {
+            music.play(MusicTrack::Kitchen);
+        }
+        let intensity = match self.parameters.kitchen_auto_crank_level() {
+            0 => 0,
+            1..=4 => 1,
+            _ => 2,
+        };
+        music.set_intensity(intensity);
+        music.update(self.sound_store.is_playing());
+    }
 }
 
-impl GameState {
-    fn update_sprite(
-        &mut self,
-        sprite: &mut Sprite,
-        _playdate: &mut Playdate,
-    ) -> Result<(), Error> {
-        let sprite_type: SpriteType = sprite.get_tag()?.into();
-        match sprite_type {
-            SpriteType::MachineCrank => self.pasta_machine.update_crank(
-                &mut self.state,
-                &self.parameters,
-                &mut self.audio_event_channel,
-            ),
-            SpriteType::MachineBody => self.pasta_machine.update(&mut self.state, &self.parameters),
-            SpriteType::FillBar => self.flour_pile.fill_bar_update(),
-            SpriteType::FlourPile => self.flour_pile.update(
-                &mut self.state,
-                &self.parameters,
-                &mut self.audio_event_channel,
-            ),
-            SpriteType::BottomBar => self.bottom_bar.update(&self.state, &mut self.menu),
-            SpriteType::Menu => self.menu.update(
-                &mut self.parameters,
-                &mut self.state,
-                &mut self.audio_event_channel,
-            ),
-            SpriteType::DoughStoreDough
-            | SpriteType::MachineDough
-            | SpriteType::AButtonIndicator => {}
+impl Scene for GameState {
+    fn enter(&mut self) {
+        self.apply_settings_changes();
+        // The kitchen doesn't run while it's covered
+        self.economy_clock.reset();
+    }
+
+    fn update_sprite(&mut self, sprite: &mut Sprite) -> Result<(), Error> {
+        if let Some(update) = self.sprite_registry.update_fn(sprite) {
+            update(self);
         }
         Ok(())
     }
@@ -189,47 +333,95 @@ impl GameState {
         sprite: &Sprite,
         _bounds: &PDRect,
         _draw_rect: &PDRect,
-        _playdate: &Playdate,
     ) -> Result<(), Error> {
-        // This function only needs to implement drawing for sprites that set "use_custom_draw"
-        let tag = sprite.get_tag()?.into();
-        match tag {
-            SpriteType::FillBar => self.flour_pile.draw_fill_bar()?,
-            _ => {}
+        if let Some(draw) = self.sprite_registry.draw_fn(sprite) {
+            draw(self)?;
         }
         Ok(())
     }
 
-    fn update(&mut self, _playdate: &mut Playdate) -> Result<(), Error> {
+    fn update(&mut self) -> Result<SceneTransition, Error> {
         let graphics = Graphics::get();
         graphics.clear_context().unwrap();
 
-        audio_events::process_events(&mut self.audio_event_channel, &mut self.sound_store);
+        self.update_music();
+        if self.settings_requested.take() {
+            let settings_scene =
+                SettingsScene::new(self.settings_screen.clone(), self.music.clone());
+            return Ok(SceneTransition::Push(Box::new(settings_scene)));
+        }
+        for _ in 0..self.economy_clock.advance() {
+            self.step_economy(self.economy_clock.step());
+        }
+        self.random_events.update(&mut self.game_events);
+        self.order_board.update(
+            &mut self.state,
+            &self.parameters,
+            &self.random_events,
+            &mut self.game_events,
+        );
+        let input_enabled = self.kitchen_input_enabled();
+        self.production_chain
+            .update(&mut self.state, &self.parameters, input_enabled);
+        self.game_events.dispatch(&mut [
+            &mut self.random_events,
+            &mut self.sound_store,
+            &mut self.effects,
+            &mut self.tutorial,
+            &mut self.hints,
+        ]);
+        self.effects.update();
         self.save_timer.update();
         if self.save_timer.just_finished() {
-            save::save_state(self.save_index, &self);
+            if let Err(e) = save::save_state(self.save_index, self) {
+                log_to_console!("Failed to save, keeping the previous save. Error: {:?}", e);
+            }
             self.save_timer.reset();
         }
 
-        self.info_overlay.borrow_mut().update();
-        Ok(())
+        let target_bounds = self
+            .tutorial
+            .target()
+            .map(|target| self.target_bounds(target));
+        self.tutorial.update(target_bounds);
+        let cheapest_upgrade = self.menu.cheapest_cost();
+        self.hints.update(
+            &self.state,
+            cheapest_upgrade.as_ref(),
+            input_enabled,
+            self.tutorial.is_running(),
+        );
+        Ok(SceneTransition::None)
     }
 }
 
-impl GameMode {
+/// The top level of the game, which hands everything on to whichever scene is active
+#[derive(Debug)]
+struct PastaGame {
+    scenes: SceneStack,
+}
+
+impl PastaGame {
     fn new(_playdate: &Playdate) -> Result<Box<Self>, Error> {
-        let level_select = LevelSelect::new();
-        Ok(Box::new(GameMode::LevelSelect(level_select)))
+        let settings = Settings::load();
+        if let Err(e) = settings.apply() {
+            log_to_console!("Failed to apply settings. Error: {:?}", e);
+        }
+        let music = Rc::new(RefCell::new(Music::new(settings.music_volume())));
+        let level_select = LevelSelect::new(music, settings);
+        Ok(Box::new(Self {
+            scenes: SceneStack::new(Box::new(level_select)),
+        }))
     }
 }
 
-impl Game for GameMode {
-    fn update_sprite(&mut self, sprite: &mut Sprite, playdate: &mut Playdate) -> Result<(), Error> {
-        match self {
-            GameMode::LevelSelect(_) => {}
-            GameMode::Game(state) => state.update_sprite(sprite, playdate)?,
-        }
-        Ok(())
+impl Game for PastaGame {
+    fn update_sprite(
+        &mut self,
+        sprite: &mut Sprite,
+        _playdate: &mut Playdate,
+    ) -> Result<(), Error> {
+        self.scenes.update_sprite(sprite)
     }
 
     fn draw_sprite(
@@ -237,29 +429,15 @@ impl Game for GameMode {
         sprite: &Sprite,
         bounds: &PDRect,
         draw_rect: &PDRect,
-        playdate: &Playdate,
+        _playdate: &Playdate,
     ) -> Result<(), Error> {
         // This function only needs to implement drawing for sprites that set "use_custom_draw"
-        match self {
-            GameMode::LevelSelect(_) => {}
-            GameMode::Game(state) => state.draw_sprite(sprite, bounds, draw_rect, playdate)?,
-        }
-        Ok(())
+        self.scenes.draw_sprite(sprite, bounds, draw_rect)
     }
 
-    fn update(&mut self, playdate: &mut Playdate) -> Result<(), Error> {
-        match self {
-            GameMode::LevelSelect(level_select) => {
-                if let Some(selected_level_idx) = level_select.update() {
-                    log_to_console!("El levelo selecte! {}", selected_level_idx);
-                    let game_state = GameState::new(selected_level_idx)?;
-                    *self = GameMode::Game(game_state);
-                }
-            }
-            GameMode::Game(state) => state.update(playdate)?,
-        }
-        Ok(())
+    fn update(&mut self, _playdate: &mut Playdate) -> Result<(), Error> {
+        self.scenes.update()
     }
 }
 
-crankstart_game!(GameMode);
+crankstart_game!(PastaGame);
@@ -1,16 +1,175 @@
-use crate::audio_events::{AudioEvent, AudioEventChannel};
-use crate::core_elements::{AutoTicker, IncrSprite};
+use crate::core_elements::{lerp, AutoTicker, IncrSprite, TextSpriteWithValue};
+use crate::crank_sound::CrankSound;
 use crate::dough_store::DoughStore;
+use crate::game_events::{GameEvent, GameEventBus};
 use crate::game_value::GameUInt;
-use crate::helpers::load_sprite_at;
-use crate::{helpers, CoreParameters, CoreState, SpriteType};
+use crate::settings::CrankDirection;
+use crate::sprite_owner::SpriteOwner;
+use crate::sprite_registry::SpriteRegistry;
+use crate::{helpers, CoreParameters, CoreState, GameState};
+use alloc::boxed::Box;
 use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::vec::Vec;
-use crankstart::graphics::{Bitmap, Graphics};
-use crankstart::sprite::{Sprite, SpriteManager};
+use core::cell::Cell;
+use crankstart::graphics::{Bitmap, Graphics, LCDColor};
+use crankstart::sprite::Sprite;
 use crankstart::system::System;
-use crankstart_sys::LCDBitmapFlip;
-use num_traits::Zero;
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDRect};
+use serde::{Deserialize, Serialize};
+
+/// Most machines the kitchen can hold, including the first
+pub const MAX_MACHINES: usize = 3;
+/// Number of stages the dough goes through in the rollers before it comes out as a sheet
+const ROLLER_STAGES: usize = 4;
+/// Degrees of cranking per roller stage for each gearbox tier
+const GEARBOX_DEGREES_PER_TICK: [f32; 4] = [360.0, 270.0, 180.0, 120.0];
+
+/// Upgrades bought for a single machine
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MachineUpgrades {
+    /// Each level of wider rollers makes an extra piece of pasta from every sheet
+    pub roller_level: usize,
+    /// Each motor level auto-cranks this machine
+    pub motor_level: usize,
+    /// Higher tiers take less cranking per roller stage
+    pub gearbox_tier: usize,
+}
+
+impl MachineUpgrades {
+    pub const MAX_ROLLER_LEVEL: usize = 4;
+    pub const MAX_MOTOR_LEVEL: usize = 10;
+    pub const MAX_GEARBOX_TIER: usize = GEARBOX_DEGREES_PER_TICK.len() - 1;
+
+    fn pasta_per_sheet(&self) -> usize {
+        1 + self.roller_level.min(Self::MAX_ROLLER_LEVEL)
+    }
+
+    fn degrees_per_tick(&self) -> f32 {
+        GEARBOX_DEGREES_PER_TICK[self.gearbox_tier.min(Self::MAX_GEARBOX_TIER)]
+    }
+}
+
+/// What's in a machine's rollers. Dough is only taken from the store on loading and a sheet is
+/// only made on ejecting, and the only way from one to the other is through every rolling stage,
+/// so each sheet uses up exactly one dough ball
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RollerStateRepr", into = "RollerStateRepr")]
+enum RollerState {
+    /// Waiting on dough, a crank tick does nothing
+    #[default]
+    Empty,
+    /// A dough ball has just been taken from the store and is being fed in
+    Loading,
+    /// Each crank tick moves the dough to the next stage
+    Rolling(usize),
+    /// The finished sheet is coming out of the bottom
+    Ejecting,
+}
+
+impl RollerState {
+    /// Transitions that happen between crank ticks. Loading and ejecting only last a single
+    /// update, and an empty machine takes dough as soon as there is some rather than waiting on
+    /// the next tick
+    fn settle(self, dough_balls: &mut GameUInt) -> Self {
+        match self {
+            RollerState::Loading => RollerState::Rolling(0),
+            RollerState::Empty | RollerState::Ejecting => {
+                if dough_balls.is_zero() {
+                    RollerState::Empty
+                } else {
+                    *dough_balls -= GameUInt::one();
+                    RollerState::Loading
+                }
+            }
+            rolling => rolling,
+        }
+    }
+
+    /// Transition for a crank tick, also returning whether a sheet was finished
+    fn tick(self) -> (Self, bool) {
+        match self {
+            RollerState::Rolling(stage) if stage + 1 < ROLLER_STAGES => {
+                (RollerState::Rolling(stage + 1), false)
+            }
+            RollerState::Rolling(_) => (RollerState::Ejecting, true),
+            other => (other, false),
+        }
+    }
+
+    /// A crank tick that lands between sheets, while loading or ejecting, settles the rollers first
+    /// so the tick isn't lost. It's only lost if there's no dough to roll
+    fn tick_settled(mut self, dough_balls: &mut GameUInt) -> (Self, bool) {
+        loop {
+            match self {
+                RollerState::Rolling(_) => return self.tick(),
+                RollerState::Empty if dough_balls.is_zero() => return (self, false),
+                _ => self = self.settle(dough_balls),
+            }
+        }
+    }
+
+    /// Roller dough image for the top and bottom of the machine, None to hide it
+    fn sprite_stages(self) -> (Option<usize>, Option<usize>) {
+        match self {
+            RollerState::Empty => (None, None),
+            RollerState::Loading => (Some(0), None),
+            RollerState::Rolling(stage) => (Some(stage), Some(stage)),
+            RollerState::Ejecting => (None, Some(ROLLER_STAGES - 1)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+enum RollerPhase {
+    Empty,
+    Loading,
+    Rolling,
+    Ejecting,
+}
+
+/// serde-json-core can only handle unit enum variants, so the rolling stage is saved alongside
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct RollerStateRepr {
+    phase: RollerPhase,
+    #[serde(default)]
+    stage: usize,
+}
+
+impl From<RollerStateRepr> for RollerState {
+    fn from(repr: RollerStateRepr) -> Self {
+        match repr.phase {
+            RollerPhase::Empty => RollerState::Empty,
+            RollerPhase::Loading => RollerState::Loading,
+            RollerPhase::Rolling => RollerState::Rolling(repr.stage.min(ROLLER_STAGES - 1)),
+            RollerPhase::Ejecting => RollerState::Ejecting,
+        }
+    }
+}
+
+impl From<RollerState> for RollerStateRepr {
+    fn from(state: RollerState) -> Self {
+        let (phase, stage) = match state {
+            RollerState::Empty => (RollerPhase::Empty, 0),
+            RollerState::Loading => (RollerPhase::Loading, 0),
+            RollerState::Rolling(stage) => (RollerPhase::Rolling, stage),
+            RollerState::Ejecting => (RollerPhase::Ejecting, 0),
+        };
+        Self { phase, stage }
+    }
+}
+
+/// The saved part of each [PastaMachine]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MachineProgress {
+    crank_pos: f32,
+    /// Cranking done towards the next roller stage
+    #[serde(default)]
+    crank_progress: f32,
+    #[serde(default)]
+    roller: RollerState,
+}
 
 #[derive(Debug)]
 struct CrankTracker {
@@ -19,9 +178,9 @@ struct CrankTracker {
 }
 
 impl CrankTracker {
-    fn new(progress_per_tick: f32) -> Self {
+    fn new(crank_progress: f32, progress_per_tick: f32) -> Self {
         Self {
-            crank_progress: 0.0,
+            crank_progress,
             progress_per_tick,
         }
     }
@@ -44,59 +203,140 @@ impl CrankTracker {
     }
 }
 
+/// One pasta machine. Only the selected machine is drawn and can be cranked by hand, but all of
+/// them keep running from their motors
+#[derive(Debug)]
+struct PastaMachine {
+    index: usize,
+    crank_pos: f32,
+    crank_tracker: CrankTracker,
+    auto_crank: AutoTicker,
+    roller: RollerState,
+    /// Degrees the motor turned the crank in the last step, for blending the crank between steps
+    auto_step: f32,
+    /// Degrees the crank has turned this frame, by hand or by motor
+    crank_moved: f32,
+}
+
+impl PastaMachine {
+    fn new(index: usize, progress: MachineProgress) -> Self {
+        Self {
+            index,
+            crank_pos: progress.crank_pos,
+            crank_tracker: CrankTracker::new(progress.crank_progress, GEARBOX_DEGREES_PER_TICK[0]),
+            auto_crank: AutoTicker::new(40.0),
+            roller: progress.roller,
+            auto_step: 0.0,
+            crank_moved: 0.0,
+        }
+    }
+
+    fn to_saved(&self) -> MachineProgress {
+        MachineProgress {
+            crank_pos: self.crank_pos,
+            crank_progress: self.crank_tracker.crank_progress,
+            roller: self.roller,
+        }
+    }
+
+    /// The first machine is also cranked by the kitchen-wide auto-cranker and cousins
+    fn auto_crank_level(&self, parameters: &CoreParameters) -> usize {
+        let motor_level = parameters.machine_upgrades(self.index).motor_level;
+        if self.index == 0 {
+            motor_level + parameters.total_auto_crank_level()
+        } else {
+            motor_level
+        }
+    }
+
+    /// Image to show for the crank, `alpha` of the way from the last step to the next
+    fn crank_idx(&self, alpha: f32) -> usize {
+        let shown_pos = helpers::wrap(
+            lerp(self.crank_pos - self.auto_step, self.crank_pos, alpha),
+            0.0,
+            360.0,
+        );
+        // 360.0 / 14.0 = 25.71428571428571
+        let macro_idx = (shown_pos / (360.0 / 28.0)) as usize;
+        if macro_idx >= 14 {
+            if macro_idx >= 27 {
+                0
+            } else {
+                27 - macro_idx
+            }
+        } else {
+            macro_idx
+        }
+    }
+
+    /// Runs the motor for `dt` seconds. Returns the number of pieces of pasta made
+    fn step(&mut self, dt: f32, state: &mut CoreState, parameters: &CoreParameters) -> usize {
+        self.auto_step = self.auto_crank.poll(self.auto_crank_level(parameters), dt);
+        self.turn(self.auto_step, state, parameters)
+    }
+
+    /// Turns the crank by `crank_move` degrees. Returns the number of pieces of pasta made
+    fn turn(
+        &mut self,
+        crank_move: f32,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+    ) -> usize {
+        self.crank_pos = helpers::wrap(self.crank_pos + crank_move, 0.0, 360.0);
+        self.crank_moved += crank_move;
+        // Only a machine that's being turned forwards takes dough from the shared store, so one
+        // that's idle or being cranked backwards doesn't sit on a dough ball
+        if crank_move <= 0.0 {
+            return 0;
+        }
+        self.roller = self.roller.settle(&mut state.dough_balls);
+        let upgrades = parameters.machine_upgrades(self.index);
+        self.crank_tracker.progress_per_tick = upgrades.degrees_per_tick();
+        if !self.crank_tracker.update(crank_move) {
+            return 0;
+        }
+        let (roller, sheet_done) = self.roller.tick_settled(&mut state.dough_balls);
+        self.roller = roller;
+        if sheet_done {
+            upgrades.pasta_per_sheet()
+        } else {
+            0
+        }
+    }
+}
+
 #[derive(Debug)]
 struct MachineCrank {
     images: Vec<Bitmap>,
     sprite: Sprite,
-    pos: f32,
-    crank_tracker: CrankTracker,
-    auto_crank: AutoTicker,
+    shown_idx: usize,
     prev_crank_angle: f32,
 }
 
 impl MachineCrank {
-    fn new(pos: (f32, f32)) -> Self {
+    fn new(pos: (f32, f32), sprites: &mut SpriteOwner) -> Self {
         System::log_to_console("Machine crank new");
         let graphics = Graphics::get();
         let images: Vec<Bitmap> = (0..14)
             .flat_map(|idx| graphics.load_bitmap(&format!("res/crank/crank{}", idx)))
             .collect();
-        let sprite_manager = SpriteManager::get_mut();
-        let mut sprite = sprite_manager.new_sprite().unwrap();
+        let mut sprite = sprites.new_sprite();
         sprite
             .set_image(images[0].clone(), LCDBitmapFlip::kBitmapUnflipped)
             .unwrap();
-        sprite.set_tag(SpriteType::MachineCrank as u8).unwrap();
         let (x, y) = pos;
         sprite.move_to(x, y).unwrap();
-        sprite_manager.add_sprite(&sprite).unwrap();
         Self {
             images,
             sprite,
-            pos: 0.0,
-            crank_tracker: CrankTracker::new(360.0),
-            auto_crank: AutoTicker::new(40.0),
+            shown_idx: 0,
             prev_crank_angle: System::get().get_crank_angle().unwrap_or(0.0),
         }
     }
 
-    fn get_idx(&self) -> usize {
-        // 360.0 / 14.0 = 25.71428571428571
-        let macro_idx = (self.pos / (360.0 / 28.0)) as usize;
-        if macro_idx >= 14 {
-            if macro_idx >= 27 {
-                0
-            } else {
-                27 - macro_idx
-            }
-        } else {
-            macro_idx
-        }
-    }
-
-    pub fn update(&mut self, parameters: &CoreParameters) -> bool {
+    /// How far the player has turned the crank since last update, if at all
+    fn poll(&mut self, direction: CrankDirection) -> Option<f32> {
         let system = System::get();
-        let angle_from_autocrank = self.auto_crank.poll(parameters.auto_crank_level);
         // There is extra paranoia here because in theory "get_crank_change" resets the value after
         // each call, but I've observed this being not true, as least in the simulator
         // As a result we monitor the crank angle and only update if it has changed
@@ -104,33 +344,34 @@ impl MachineCrank {
         let crank_angle = system.get_crank_angle().unwrap_or(0.0);
         let angle_changed = crank_angle != self.prev_crank_angle;
         self.prev_crank_angle = crank_angle;
-        let crank_move = match (angle_changed, angle_from_autocrank) {
-            (true, _) => Some(crank_change),
-            (false, 0.0) => None,
-            (false, _) => Some(angle_from_autocrank),
-        };
-        if let Some(crank_by_angle) = crank_move {
-            self.pos = helpers::wrap(self.pos + crank_by_angle, 0.0, 360.0);
-            let idx = self.get_idx();
-            self.sprite
-                .set_image(self.images[idx].clone(), LCDBitmapFlip::kBitmapUnflipped)
-                .unwrap();
-            self.crank_tracker.update(crank_by_angle)
+        if angle_changed {
+            Some(direction.apply(crank_change))
         } else {
-            false
+            None
         }
     }
+
+    fn show(&mut self, idx: usize) {
+        if idx == self.shown_idx {
+            return;
+        }
+        self.shown_idx = idx;
+        self.sprite
+            .set_image(self.images[idx].clone(), LCDBitmapFlip::kBitmapUnflipped)
+            .unwrap();
+    }
 }
 
+/// An optional part bolted onto the machine, only shown once it's been bought
 #[derive(Debug)]
-pub struct Gearbox {
+pub struct Attachment {
     sprite: Sprite,
     visible: bool,
 }
 
-impl Gearbox {
-    fn new(x: f32, y: f32) -> Self {
-        let mut sprite = load_sprite_at("res/crank-gearbox", x, y, None);
+impl Attachment {
+    fn new(image_path: &str, x: f32, y: f32, sprites: &mut SpriteOwner) -> Self {
+        let mut sprite = sprites.load_sprite_at(image_path, x, y);
         sprite.set_visible(false).unwrap();
         Self {
             sprite,
@@ -146,89 +387,366 @@ impl Gearbox {
     }
 }
 
+/// All of the kitchen's pasta machines, drawn as the currently selected one
 #[derive(Debug)]
 pub struct PastaMachineState {
+    machines: Vec<PastaMachine>,
+    selected: usize,
+    /// Set from the system menu, as its callbacks can't borrow the game state
+    switch_requested: Rc<Cell<bool>>,
+    crank_direction: CrankDirection,
+    _sprites: SpriteOwner,
     crank: MachineCrank,
-    gearbox: Gearbox,
+    crank_sound: CrankSound,
+    gearbox: Attachment,
+    cutter: Attachment,
     body_sprite: Sprite,
     top_dough: IncrSprite,
     bottom_dough: IncrSprite,
+    label: TextSpriteWithValue<String>,
     dough_store: DoughStore,
 }
 
 impl PastaMachineState {
-    pub fn new() -> Self {
+    pub fn new(progress: Vec<MachineProgress>) -> Self {
         let y = 71.0;
         let x = 284.0;
+        let mut sprites = SpriteOwner::new();
         let body_sprite = {
-            let sprite_manager = SpriteManager::get_mut();
-            let mut sprite = sprite_manager.new_sprite().unwrap();
+            let mut sprite = sprites.new_sprite();
             let image = Graphics::get().load_bitmap("res/machine_body").unwrap();
             sprite
                 .set_image(image, LCDBitmapFlip::kBitmapUnflipped)
                 .unwrap();
             sprite.move_to(x, y).unwrap();
-            sprite.set_tag(SpriteType::MachineBody as u8).unwrap();
-            sprite_manager.add_sprite(&sprite).unwrap();
             sprite
         };
         let crank_x = x + 38.0 + 11.0;
         let gear_x = x - 38.0 - 5.0;
-        let crank = MachineCrank::new((crank_x, y - 15.0));
-        let gearbox = Gearbox::new(gear_x, y - 15.0);
+        let crank = MachineCrank::new((crank_x, y - 15.0), &mut sprites);
+        let gearbox = Attachment::new("res/crank-gearbox", gear_x, y - 15.0, &mut sprites);
+        let cutter = Attachment::new("res/cutter", x, y + 33.0, &mut sprites);
         let top_dough = IncrSprite::new(
             (x - 1.0, y - 33.0),
             "res/roller_dough/roller_dough",
-            4,
-            SpriteType::MachineDough,
+            ROLLER_STAGES,
         );
         let bottom_dough = IncrSprite::new(
             (x - 1.0, y + 21.0),
             "res/roller_dough/roller_dough_bottom",
-            4,
-            SpriteType::MachineDough,
+            ROLLER_STAGES,
         );
-        let mut dough_store = DoughStore::new((280.0, 160.0));
-        Self {
+        let mut label = sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        label.get_sprite_mut().move_to(x, y + 52.0).unwrap();
+        let label = TextSpriteWithValue::new(label, String::new(), Box::new(String::clone));
+        let dough_store = DoughStore::new((280.0, 160.0));
+        let machines = progress
+            .into_iter()
+            .take(MAX_MACHINES)
+            .enumerate()
+            .map(|(i, progress)| PastaMachine::new(i, progress))
+            .collect();
+        let mut s = Self {
+            machines,
+            selected: 0,
+            switch_requested: Rc::new(Cell::new(false)),
+            crank_direction: CrankDirection::Forward,
+            _sprites: sprites,
             crank,
+            crank_sound: CrankSound::new(),
             gearbox,
+            cutter,
             body_sprite,
             top_dough,
             bottom_dough,
+            label,
             dough_store,
+        };
+        s.add_missing_machines(1);
+        s
+    }
+
+    pub fn to_saved(&self) -> Vec<MachineProgress> {
+        self.machines.iter().map(PastaMachine::to_saved).collect()
+    }
+
+    /// Handle for the system menu to ask for the next machine to be shown
+    pub fn switch_request(&self) -> Rc<Cell<bool>> {
+        self.switch_requested.clone()
+    }
+
+    pub fn crank_bounds(&self) -> PDRect {
+        self.crank.sprite.get_bounds().unwrap()
+    }
+
+    pub fn set_crank_direction(&mut self, direction: CrankDirection) {
+        self.crank_direction = direction;
+    }
+
+    pub fn set_sound_volume(&mut self, volume: f32) {
+        self.crank_sound.set_volume(volume);
+    }
+
+    fn add_missing_machines(&mut self, count: usize) {
+        while self.machines.len() < count.min(MAX_MACHINES) {
+            let index = self.machines.len();
+            self.machines
+                .push(PastaMachine::new(index, MachineProgress::default()));
         }
     }
+
+    /// Stores the pasta machine `machine` made and lets everyone know
+    fn store_made(
+        machine: usize,
+        made: usize,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        game_events: &mut GameEventBus,
+    ) {
+        if made == 0 {
+            return;
+        }
+        let pasta = parameters.machine_output();
+        for _ in 0..made {
+            state.store_finished_pasta(pasta, parameters);
+        }
+        game_events.push(GameEvent::SheetMade {
+            machine,
+            pasta,
+            count: made,
+        });
+    }
+
+    /// Runs every machine's motor for `dt` seconds
+    pub fn step(
+        &mut self,
+        dt: f32,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        game_events: &mut GameEventBus,
+    ) {
+        for (i, machine) in self.machines.iter_mut().enumerate() {
+            let made = machine.step(dt, state, parameters);
+            Self::store_made(i, made, state, parameters, game_events);
+        }
+    }
+
+    /// Applies the player's cranking to the selected machine
     pub fn update_crank(
         &mut self,
         state: &mut CoreState,
         parameters: &CoreParameters,
-        events: &mut AudioEventChannel,
+        game_events: &mut GameEventBus,
     ) {
-        let crank_ticked = self.crank.update(parameters);
-        if crank_ticked {
-            System::log_to_console("Crank ticked");
-            let top_dough_pre = self.top_dough.get_idx();
-            // TODO: Don't like this. Both should always be in sync so should treat them this way
-            self.top_dough.incr(false);
-            self.bottom_dough.incr(false);
-
-            if top_dough_pre == Some(3) && self.top_dough.get_idx().is_none() {
-                state.add_money_big(parameters.pasta_price.clone());
-                events.push(AudioEvent::MoneyGained);
+        self.add_missing_machines(parameters.machine_count());
+        if let Some(crank_change) = self.crank.poll(self.crank_direction) {
+            game_events.push(GameEvent::Cranked {
+                backwards: crank_change < 0.0,
+            });
+            let machine = &mut self.machines[self.selected];
+            let made = machine.turn(crank_change, state, parameters);
+            Self::store_made(self.selected, made, state, parameters, game_events);
+        }
+        // Only the machine on screen is heard
+        self.crank_sound
+            .update(self.machines[self.selected].crank_moved);
+        for machine in self.machines.iter_mut() {
+            machine.crank_moved = 0.0;
+        }
+    }
+
+    /// `alpha` is how far the frame is between economy steps
+    fn update_visuals(&mut self, parameters: &CoreParameters, alpha: f32) {
+        let machine = &self.machines[self.selected];
+        self.crank.show(machine.crank_idx(alpha));
+        let (top_stage, bottom_stage) = machine.roller.sprite_stages();
+        self.top_dough.set_idx(top_stage);
+        self.bottom_dough.set_idx(bottom_stage);
+        self.gearbox
+            .set_visible(machine.auto_crank_level(parameters) > 0);
+        self.cutter.set_visible(parameters.has_cutter);
+        let label = if self.machines.len() > 1 {
+            format!("Machine {}/{}", self.selected + 1, self.machines.len())
+        } else {
+            String::new()
+        };
+        self.label.update_value(&label);
+    }
+
+    pub fn update(&mut self, parameters: &CoreParameters, alpha: f32) {
+        if self.switch_requested.replace(false) {
+            self.selected = (self.selected + 1) % self.machines.len();
+        }
+        self.update_visuals(parameters, alpha);
+    }
+
+    pub fn register_sprites(&mut self, registry: &mut SpriteRegistry<GameState>) {
+        registry.on_update(&mut self.crank.sprite, |game| {
+            game.pasta_machine.update_crank(
+                &mut game.state,
+                &game.parameters,
+                &mut game.game_events,
+            )
+        });
+        registry.on_update(&mut self.body_sprite, |game| {
+            game.pasta_machine
+                .update(&game.parameters, game.economy_clock.alpha())
+        });
+        registry.on_update(self.dough_store.sprite_mut(), |game| {
+            game.pasta_machine.dough_store.update(&game.state)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dough(count: usize) -> GameUInt {
+        GameUInt::from(count)
+    }
+
+    #[test]
+    fn empty_with_no_dough_stays_empty() {
+        let mut dough_balls = dough(0);
+        assert_eq!(
+            RollerState::Empty.settle(&mut dough_balls),
+            RollerState::Empty
+        );
+        assert_eq!(dough_balls, dough(0));
+        assert_eq!(RollerState::Empty.tick(), (RollerState::Empty, false));
+    }
+
+    #[test]
+    fn empty_loads_a_dough_ball() {
+        let mut dough_balls = dough(2);
+        assert_eq!(
+            RollerState::Empty.settle(&mut dough_balls),
+            RollerState::Loading
+        );
+        assert_eq!(dough_balls, dough(1));
+    }
+
+    #[test]
+    fn loading_starts_rolling() {
+        let mut dough_balls = dough(2);
+        assert_eq!(
+            RollerState::Loading.settle(&mut dough_balls),
+            RollerState::Rolling(0)
+        );
+        // The dough was already taken when loading began
+        assert_eq!(dough_balls, dough(2));
+    }
+
+    #[test]
+    fn rolling_moves_through_each_stage() {
+        for stage in 0..ROLLER_STAGES - 1 {
+            assert_eq!(
+                RollerState::Rolling(stage).tick(),
+                (RollerState::Rolling(stage + 1), false)
+            );
+            let mut dough_balls = dough(1);
+            assert_eq!(
+                RollerState::Rolling(stage).settle(&mut dough_balls),
+                RollerState::Rolling(stage)
+            );
+            assert_eq!(dough_balls, dough(1));
+        }
+    }
+
+    #[test]
+    fn last_stage_ejects_a_sheet() {
+        assert_eq!(
+            RollerState::Rolling(ROLLER_STAGES - 1).tick(),
+            (RollerState::Ejecting, true)
+        );
+    }
+
+    #[test]
+    fn ejecting_with_an_empty_store_goes_empty() {
+        let mut dough_balls = dough(0);
+        assert_eq!(
+            RollerState::Ejecting.settle(&mut dough_balls),
+            RollerState::Empty
+        );
+    }
+
+    #[test]
+    fn ejecting_loads_the_next_dough_ball() {
+        let mut dough_balls = dough(1);
+        assert_eq!(
+            RollerState::Ejecting.settle(&mut dough_balls),
+            RollerState::Loading
+        );
+        assert_eq!(dough_balls, dough(0));
+    }
+
+    #[test]
+    fn ticks_between_sheets_are_not_lost() {
+        let mut dough_balls = dough(2);
+        assert_eq!(
+            RollerState::Loading.tick_settled(&mut dough_balls),
+            (RollerState::Rolling(1), false)
+        );
+        assert_eq!(dough_balls, dough(2));
+        assert_eq!(
+            RollerState::Ejecting.tick_settled(&mut dough_balls),
+            (RollerState::Rolling(1), false)
+        );
+        assert_eq!(dough_balls, dough(1));
+        assert_eq!(
+            RollerState::Empty.tick_settled(&mut dough_balls),
+            (RollerState::Rolling(1), false)
+        );
+        assert_eq!(dough_balls, dough(0));
+        assert_eq!(
+            RollerState::Empty.tick_settled(&mut dough_balls),
+            (RollerState::Empty, false)
+        );
+    }
+
+    #[test]
+    fn every_tick_counts_when_there_is_dough() {
+        let mut dough_balls = dough(3);
+        let mut roller = RollerState::Empty;
+        let mut sheets = 0;
+        for _ in 0..3 * ROLLER_STAGES {
+            let (next, sheet_done) = roller.tick_settled(&mut dough_balls);
+            roller = next;
+            if sheet_done {
+                sheets += 1;
             }
+        }
+        assert_eq!(sheets, 3);
+        assert_eq!(dough_balls, dough(0));
+    }
 
-            if !self.top_dough.is_active() {
-                // try and replenish
-                if !state.dough_balls.is_zero() {
-                    state.dough_balls -= GameUInt::one();
-                    self.top_dough.reset();
-                    self.bottom_dough.reset();
-                }
+    #[test]
+    fn each_sheet_uses_one_dough_ball() {
+        let mut dough_balls = dough(3);
+        let mut roller = RollerState::Empty;
+        let mut sheets = 0;
+        for _ in 0..100 {
+            roller = roller.settle(&mut dough_balls);
+            let (next, sheet_done) = roller.tick();
+            roller = next;
+            if sheet_done {
+                sheets += 1;
             }
         }
+        assert_eq!(sheets, 3);
+        assert_eq!(dough_balls, dough(0));
+        assert_eq!(roller.settle(&mut dough_balls), RollerState::Empty);
     }
-    pub fn update(&mut self, state: &mut CoreState, parameters: &CoreParameters) {
-        self.dough_store.update(state);
-        self.gearbox.set_visible(parameters.auto_crank_level > 0)
+
+    #[test]
+    fn cranking_backwards_takes_no_dough() {
+        let mut machine = PastaMachine::new(0, MachineProgress::default());
+        let mut state = CoreState::default();
+        state.dough_balls = dough(3);
+        let parameters = CoreParameters::default();
+        assert_eq!(machine.turn(-30.0, &mut state, &parameters), 0);
+        assert_eq!(state.dough_balls, dough(3));
+        assert_eq!(machine.roller, RollerState::Empty);
     }
 }
@@ -1,40 +1,48 @@
-use crate::audio_events::{AudioEvent, AudioEventChannel};
 use crate::core_elements::{CoreParameters, CoreState, VisibilityState};
-use crate::game_value::{GameUInt, GameValue};
-use crate::{GameState, SpriteType};
+use crate::game_events::{GameEvent, GameEventBus};
+use crate::game_value::{GameUInt, GameValue, NumberFormat};
+use crate::sprite_owner::SpriteOwner;
+use crate::sprite_registry::SpriteRegistry;
+use crate::tween::{Easing, Tween};
+use crate::GameState;
 use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::fmt::Debug;
-use core::ops::{RemAssign, SubAssign};
-use crankstart::graphics::{Bitmap, Graphics};
+use core::ops::RemAssign;
+use crankstart::graphics::{Bitmap, Graphics, LCDColor};
 use crankstart::log_to_console;
 use crankstart::sprite::{Sprite, TextSprite};
 use crankstart::system::System;
-use crankstart_sys::{LCDBitmapFlip, PDButtons};
-use num_bigint::BigUint;
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons, PDRect};
 
 #[derive(Debug)]
 pub struct Menu {
     state: VisibilityState,
+    _sprites: SpriteOwner,
     background: Sprite,
     menu_items: Vec<MenuItem>,
     selected_item_index: usize,
     pressed_item_index: usize,
+    /// How far the shop has slid in, from 0.0 hidden to 1.0 fully shown
+    slide: Tween,
+    /// Slide amount the background was last moved to
+    placed_shown: f32,
+    /// The number format the item costs were last drawn with
+    shown_format: NumberFormat,
 }
 
 impl Menu {
     const ITEM_Y_START: f32 = 35.0;
     const ITEM_SPACING: f32 = 72.0;
     const ITEM_MAX_DISPLAY_Y: f32 = 170.0;
+    const BACKGROUND_HIDDEN_X: f32 = -95.5;
+    const BACKGROUND_VISIBLE_X: f32 = 95.5;
+    const SLIDE_SECS: f32 = 0.25;
     pub fn new() -> Self {
-        let mut background = crate::helpers::load_sprite_at(
-            "res/menu_background",
-            -95.5,
-            95.50,
-            Some(SpriteType::Menu as u8),
-        );
+        let mut sprites = SpriteOwner::new();
+        let mut background = sprites.load_sprite_at("res/menu_background", -95.5, 95.50);
         background.set_z_index(9).unwrap();
         let mut menu_items = Vec::new();
         let num_items = 30;
@@ -47,23 +55,28 @@ impl Menu {
         for (i, data) in menu_item_data_prefabs::all().into_iter().enumerate() {
             let y = Self::ITEM_Y_START + (i as f32 * Self::ITEM_SPACING);
             let offset = BoundedPosition::new(y, y - max_scroll_amount, y);
-            let item = MenuItem::new(data, offset);
+            let item = MenuItem::new(data, offset, &mut sprites);
             menu_items.push(item);
         }
         let mut s = Self {
             state: VisibilityState::Hidden,
+            _sprites: sprites,
             background,
             menu_items,
             selected_item_index: 0,
             pressed_item_index: 0,
+            slide: Tween::settled(0.0, Self::SLIDE_SECS, Easing::EaseOut),
+            placed_shown: 0.0,
+            shown_format: NumberFormat::current(),
         };
         s.change_selected_item(0);
         s
     }
 
     pub fn init_counts(&mut self, counts: &[usize]) {
-        if self.menu_items.len() != counts.len() {
-            panic!("init_counts: counts.len() != menu_items.len(). Save file invalid?");
+        // Saves from before newer items were added will have fewer counts, those items start at 0
+        if counts.len() > self.menu_items.len() {
+            panic!("init_counts: counts.len() > menu_items.len(). Save file invalid?");
         }
         for (i, count) in counts.iter().enumerate() {
             self.menu_items[i].data.count = *count;
@@ -75,6 +88,16 @@ impl Menu {
         self.menu_items.iter().map(|item| item.data.count).collect()
     }
 
+    /// Cost of the cheapest item that can still be bought, if any can. Switching a bought toggle
+    /// is free, so those don't count, and nor do upgrades for machines that aren't owned yet
+    pub fn cheapest_cost(&self) -> Option<GameUInt> {
+        self.menu_items
+            .iter()
+            .filter(|item| !item.locked && !item.data.is_switchable())
+            .filter_map(|item| item.data.cost())
+            .min()
+    }
+
     fn scroll(&mut self, change: f32) {
         System::log_to_console("Scrolling!");
         for item in self.menu_items.iter_mut() {
@@ -98,28 +121,50 @@ impl Menu {
         }
     }
 
+    pub fn selected_item_bounds(&self) -> PDRect {
+        self.menu_items[self.selected_item_index]
+            .sprite
+            .get_bounds()
+            .unwrap()
+    }
+
+    pub fn is_visible(&self) -> bool {
+        matches!(self.state, VisibilityState::Visible)
+    }
+
+    /// Slides the shop in or out, buttons only work once it's fully shown
     pub fn set_state(&mut self, state: VisibilityState) {
         self.state = state;
-        match state {
-            VisibilityState::Hidden => {
-                self.background.move_to(-95.5, 95.50).unwrap();
-                for item in &mut self.menu_items {
-                    item.set_state(VisibilityState::Hidden);
-                }
-            }
-            VisibilityState::Visible => {
-                self.background.move_to(95.5, 95.50).unwrap();
-                for item in &mut self.menu_items {
-                    item.set_state(VisibilityState::Visible);
-                }
-            }
+        let target = match state {
+            VisibilityState::Hidden => 0.0,
+            VisibilityState::Visible => 1.0,
+        };
+        self.slide.retarget(target);
+    }
+
+    fn update_positions(&mut self) {
+        let shown = self.slide.value();
+        let lerp = |hidden: f32, visible: f32| hidden + (visible - hidden) * shown;
+        if shown != self.placed_shown {
+            self.placed_shown = shown;
+            self.background
+                .move_to(
+                    lerp(Self::BACKGROUND_HIDDEN_X, Self::BACKGROUND_VISIBLE_X),
+                    95.50,
+                )
+                .unwrap();
+        }
+        let item_x = lerp(MenuItem::HIDDEN_X, MenuItem::VISIBLE_X);
+        let now = System::get().get_elapsed_time().unwrap_or(0.0);
+        for item in self.menu_items.iter_mut() {
+            item.update_position(item_x, now);
         }
     }
     fn update_internal(
         &mut self,
         state: &mut CoreState,
         parameters: &mut CoreParameters,
-        audio_events: &mut AudioEventChannel,
+        game_events: &mut GameEventBus,
     ) {
         let (_, pressed, released) = System::get().get_button_state().unwrap();
         if (pressed & PDButtons::kButtonUp).0 != 0 {
@@ -130,72 +175,140 @@ impl Menu {
 
         if (pressed & PDButtons::kButtonA).0 != 0 {
             System::log_to_console("Pressed A");
-            if self.menu_items[self.selected_item_index].press_and_trigger(state, parameters) {
+            let upgrade = self.selected_item_index;
+            if self.menu_items[upgrade].press_and_trigger(state, parameters) {
                 System::log_to_console(&format!(
                     "Pressed A, cost: {}",
                     self.menu_items[self.selected_item_index].data.cost_str()
                 ));
                 self.menu_items[self.selected_item_index].set_pressed(true, true);
                 self.pressed_item_index = self.selected_item_index;
-                audio_events.push(AudioEvent::UpgradeBought);
+                self.menu_items[upgrade].start_bounce();
+                game_events.push(GameEvent::UpgradeBought {
+                    upgrade,
+                    count: self.menu_items[upgrade].data.count,
+                });
             } else {
-                audio_events.push(AudioEvent::UpgradeDenied);
+                game_events.push(GameEvent::UpgradeDenied { upgrade });
             }
         } else if (released & PDButtons::kButtonA).0 != 0 {
             self.menu_items[self.pressed_item_index]
                 .set_pressed(false, self.pressed_item_index == self.selected_item_index);
         }
     }
+
+    pub fn register_sprites(&mut self, registry: &mut SpriteRegistry<GameState>) {
+        registry.on_update(&mut self.background, |game| {
+            game.menu
+                .update(&mut game.parameters, &mut game.state, &mut game.game_events)
+        });
+    }
+
     pub fn update(
         &mut self,
         parameters: &mut CoreParameters,
         state: &mut CoreState,
-        audio_events: &mut AudioEventChannel,
+        game_events: &mut GameEventBus,
     ) {
+        for item in self.menu_items.iter_mut() {
+            item.set_locked(item.data.is_locked(parameters));
+        }
+        if self.shown_format != NumberFormat::current() {
+            self.shown_format = NumberFormat::current();
+            for item in self.menu_items.iter_mut() {
+                item.update_text();
+            }
+        }
+        self.update_positions();
         // Only process key presses if enabled
         match self.state {
             VisibilityState::Hidden => {}
-            VisibilityState::Visible => {
-                self.update_internal(state, parameters, audio_events);
+            VisibilityState::Visible if self.slide.is_finished() => {
+                self.update_internal(state, parameters, game_events);
             }
+            VisibilityState::Visible => {}
         }
     }
 }
 mod menu_item_data_prefabs {
+    use crate::game_value::GameDecimal;
+    use crate::machine::{MachineUpgrades, MAX_MACHINES};
     use crate::menu::MenuItemData;
+    use crate::production::DryingRack;
     use crate::GameUInt;
     use alloc::boxed::Box;
+    use alloc::format;
     use alloc::vec;
     use alloc::vec::Vec;
-    use num_bigint::BigUint;
 
     /// TODO: wrap a cost_fn helper that takes a max count to avoid the None check?
     pub(super) fn all() -> Vec<MenuItemData> {
-        vec![
+        let mut items = vec![
             pasta_sell_price(),
             dough_tick(),
             auto_cranker(),
             kneading_grannies(),
-        ]
+            nonnas(),
+            apprentices(),
+            cranking_cousins(),
+            delivery_boys(),
+            egg_pasta_recipe(),
+            flour_sack(),
+            flour_mill(),
+            egg_cracker(),
+            pasta_cutter(),
+            drying_rack(),
+            rack_hand(),
+            rack_space(),
+            pasta_machine(),
+        ];
+        for machine in 0..MAX_MACHINES {
+            items.push(wider_rollers(machine));
+            items.push(machine_motor(machine));
+            items.push(machine_gearbox(machine));
+        }
+        items
+    }
+
+    /// Cost of an item that can only be bought once
+    fn one_off_cost(cost: usize, count: usize) -> Option<GameUInt> {
+        if count > 0 {
+            return None;
+        }
+        Some(GameUInt::from(cost))
+    }
+
+    /// Cost of `base * growth^count`, or None once `max_count` has been bought
+    fn scaled_cost(
+        base: usize,
+        growth: GameDecimal,
+        count: usize,
+        max_count: usize,
+    ) -> Option<GameUInt> {
+        if count > max_count {
+            return None;
+        }
+        Some(GameUInt::from(base).mul_decimal(&growth.pow(count as u32)))
     }
 
     fn pasta_cost(count: u32) -> GameUInt {
-        (BigUint::from(20usize) + BigUint::from(20usize).pow(count)).into()
+        GameUInt::from(20usize) + GameUInt::from(20usize).pow(count)
     }
     fn pasta_sell_price() -> MenuItemData {
         MenuItemData {
             name: "Pasta Sell Price".into(),
             description: "Sell pasta for more".into(),
             count: 0,
+            toggle: false,
+            machine: None,
             cost_fn: Box::new(|count| {
                 if count > 10 {
                     return None;
                 }
-                let cost = pasta_cost(count as u32) * GameUInt::from(6usize);
-                Some(cost.into())
+                Some(pasta_cost(count as u32) * 6)
             }),
             on_buy_fn: Box::new(|count, _state, parameters| {
-                parameters.pasta_price = pasta_cost(count as u32);
+                parameters.pasta_price = pasta_cost(count as u32).into();
             }),
         }
     }
@@ -204,12 +317,13 @@ mod menu_item_data_prefabs {
             name: "Knead for Speed".into(),
             description: "Knead faster".into(),
             count: 0,
+            toggle: false,
+            machine: None,
             cost_fn: Box::new(|count| {
                 if count > 10 {
                     return None;
                 }
-                let cost: BigUint = BigUint::from(10usize).pow(count as u32);
-                Some(cost.into())
+                Some(GameUInt::from(10usize).pow(count as u32))
             }),
             on_buy_fn: Box::new(|count, _state, parameters| {
                 parameters.knead_tick_size = 0.01 + (count as f32 * 0.01);
@@ -222,12 +336,13 @@ mod menu_item_data_prefabs {
             name: "Auto-cranker".into(),
             description: "Automatically crank".into(),
             count: 0,
+            toggle: false,
+            machine: None,
             cost_fn: Box::new(|count| {
                 if count > 10 {
                     return None;
                 }
-                let cost: BigUint = BigUint::from(10usize).pow(count as u32);
-                Some(cost.into())
+                Some(GameUInt::from(10usize).pow(count as u32))
             }),
             on_buy_fn: Box::new(|count, _state, parameters| parameters.auto_crank_level = count),
         }
@@ -238,22 +353,258 @@ mod menu_item_data_prefabs {
             name: "Kneading Grans".into(),
             description: "Hire Grans to Knead".into(),
             count: 0,
+            toggle: false,
+            machine: None,
             cost_fn: Box::new(|count| {
                 if count > 10 {
                     return None;
                 }
-                let cost: BigUint = BigUint::from(10usize).pow(count as u32);
-                Some(cost.into())
+                Some(GameUInt::from(10usize).pow(count as u32))
             }),
             on_buy_fn: Box::new(|count, _state, parameters| parameters.auto_knead_level = count),
         }
     }
+
+    fn nonnas() -> MenuItemData {
+        MenuItemData {
+            name: "Nonnas".into(),
+            description: "Knead faster than Grans".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| scaled_cost(200, GameDecimal::from_ratio(5, 2), count, 10)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.nonna_level = count),
+        }
+    }
+
+    fn apprentices() -> MenuItemData {
+        MenuItemData {
+            name: "Apprentices".into(),
+            description: "Cheap, but slow kneaders".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| scaled_cost(5, GameDecimal::from_ratio(8, 5), count, 20)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.apprentice_level = count),
+        }
+    }
+
+    fn cranking_cousins() -> MenuItemData {
+        MenuItemData {
+            name: "Cranking Cousins".into(),
+            description: "Hire family to crank".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| scaled_cost(500, GameDecimal::from_whole(3), count, 10)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.cousin_level = count),
+        }
+    }
+
+    fn delivery_boys() -> MenuItemData {
+        MenuItemData {
+            name: "Delivery Boys".into(),
+            description: "Sell pasta for +10% each".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| scaled_cost(1000, GameDecimal::from_whole(4), count, 10)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.delivery_boy_level = count),
+        }
+    }
+
+    fn egg_pasta_recipe() -> MenuItemData {
+        MenuItemData {
+            name: "Egg Pasta".into(),
+            description: "Double price, needs flour & eggs".into(),
+            count: 0,
+            // Can be switched back off, so running out of eggs doesn't stop dough being made
+            toggle: true,
+            machine: None,
+            cost_fn: Box::new(|count| one_off_cost(2000, count)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.egg_pasta = count % 2 == 1),
+        }
+    }
+
+    fn flour_sack() -> MenuItemData {
+        MenuItemData {
+            name: "Flour Sack".into(),
+            description: "25 flour for egg pasta".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|_count| Some(GameUInt::from(50usize))),
+            on_buy_fn: Box::new(|_count, state, _parameters| state.flour += 25),
+        }
+    }
+
+    fn flour_mill() -> MenuItemData {
+        MenuItemData {
+            name: "Flour Mill".into(),
+            description: "Mill flour automatically".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| scaled_cost(300, GameDecimal::from_whole(2), count, 10)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.flour_mill_level = count),
+        }
+    }
+
+    fn egg_cracker() -> MenuItemData {
+        MenuItemData {
+            name: "Egg Cracker".into(),
+            description: "Crack eggs automatically".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| scaled_cost(300, GameDecimal::from_whole(2), count, 10)),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.egg_cracker_level = count),
+        }
+    }
+
+    fn pasta_cutter() -> MenuItemData {
+        MenuItemData {
+            name: "Pasta Cutter".into(),
+            description: "Cut sheets into tagliatelle".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| one_off_cost(5000, count)),
+            on_buy_fn: Box::new(|_count, _state, parameters| parameters.has_cutter = true),
+        }
+    }
+
+    fn drying_rack() -> MenuItemData {
+        MenuItemData {
+            name: "Drying Rack".into(),
+            description: "Dry pasta to sell for more".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| one_off_cost(3000, count)),
+            on_buy_fn: Box::new(|_count, _state, parameters| parameters.has_drying_rack = true),
+        }
+    }
+
+    fn rack_hand() -> MenuItemData {
+        MenuItemData {
+            name: "Rack Hand".into(),
+            description: "Collects dried pasta".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| one_off_cost(8000, count)),
+            on_buy_fn: Box::new(|_count, _state, parameters| parameters.has_rack_hand = true),
+        }
+    }
+
+    fn rack_space() -> MenuItemData {
+        MenuItemData {
+            name: "Rack Space".into(),
+            description: "More room on the drying rack".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| {
+                scaled_cost(
+                    2000,
+                    GameDecimal::from_whole(3),
+                    count,
+                    DryingRack::MAX_SLOT_LEVEL - 1,
+                )
+            }),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.rack_slot_level = count),
+        }
+    }
+
+    fn pasta_machine() -> MenuItemData {
+        MenuItemData {
+            name: "Pasta Machine".into(),
+            description: "Another machine to crank".into(),
+            count: 0,
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(|count| {
+                scaled_cost(10000, GameDecimal::from_whole(10), count, MAX_MACHINES - 2)
+            }),
+            on_buy_fn: Box::new(|count, _state, parameters| parameters.extra_machines = count),
+        }
+    }
+
+    /// Upgrades are bought per machine, so each machine gets its own set of menu items
+    fn wider_rollers(machine: usize) -> MenuItemData {
+        MenuItemData {
+            name: format!("Rollers (M{})", machine + 1),
+            description: "More pasta from each sheet".into(),
+            count: 0,
+            toggle: false,
+            machine: Some(machine),
+            cost_fn: Box::new(|count| {
+                scaled_cost(
+                    1500,
+                    GameDecimal::from_whole(4),
+                    count,
+                    MachineUpgrades::MAX_ROLLER_LEVEL - 1,
+                )
+            }),
+            on_buy_fn: Box::new(move |count, _state, parameters| {
+                parameters.machine_upgrades_mut(machine).roller_level = count
+            }),
+        }
+    }
+
+    fn machine_motor(machine: usize) -> MenuItemData {
+        MenuItemData {
+            name: format!("Motor (M{})", machine + 1),
+            description: "Auto-crank this machine".into(),
+            count: 0,
+            toggle: false,
+            machine: Some(machine),
+            cost_fn: Box::new(|count| {
+                scaled_cost(
+                    400,
+                    GameDecimal::from_ratio(5, 2),
+                    count,
+                    MachineUpgrades::MAX_MOTOR_LEVEL - 1,
+                )
+            }),
+            on_buy_fn: Box::new(move |count, _state, parameters| {
+                parameters.machine_upgrades_mut(machine).motor_level = count
+            }),
+        }
+    }
+
+    fn machine_gearbox(machine: usize) -> MenuItemData {
+        MenuItemData {
+            name: format!("Gearbox (M{})", machine + 1),
+            description: "Less cranking per sheet".into(),
+            count: 0,
+            toggle: false,
+            machine: Some(machine),
+            cost_fn: Box::new(|count| {
+                scaled_cost(
+                    800,
+                    GameDecimal::from_whole(5),
+                    count,
+                    MachineUpgrades::MAX_GEARBOX_TIER - 1,
+                )
+            }),
+            on_buy_fn: Box::new(move |count, _state, parameters| {
+                parameters.machine_upgrades_mut(machine).gearbox_tier = count
+            }),
+        }
+    }
 }
 
 struct MenuItemData {
     name: String,
     description: String,
     count: usize,
+    /// Once bought, pressing again switches it off and on for free. The count is still kept, so
+    /// odd counts are on and even counts are off
+    toggle: bool,
+    /// The machine a per-machine upgrade is for, it can't be bought until that machine is owned
+    machine: Option<usize>,
     cost_fn: Box<dyn Fn(usize) -> Option<GameUInt>>,
     // TODO: Think about how this buy_fn is deterministic (in terms of saving an loading state) as
     // well as not overwriting other items (i.e. if both would change/set dough tick size)
@@ -266,6 +617,8 @@ impl Debug for MenuItemData {
             .field("name", &self.name)
             .field("description", &self.description)
             .field("count", &self.count)
+            .field("toggle", &self.toggle)
+            .field("machine", &self.machine)
             .field("cost_fn", &"<opaque")
             .field("on_buy_fn", &"<opaque")
             .finish()
@@ -274,9 +627,33 @@ impl Debug for MenuItemData {
 
 impl MenuItemData {
     fn cost(&self) -> Option<GameUInt> {
+        if self.is_switchable() {
+            return Some(GameUInt::default());
+        }
         (self.cost_fn)(self.count)
     }
+
+    fn is_locked(&self, parameters: &CoreParameters) -> bool {
+        self.machine
+            .is_some_and(|machine| parameters.machine_count() <= machine)
+    }
+
+    fn is_switchable(&self) -> bool {
+        self.toggle && self.count > 0
+    }
+
+    fn count_str(&self) -> String {
+        match (self.toggle, self.count % 2) {
+            (true, 1) => "On".into(),
+            (true, _) => "Off".into(),
+            (false, _) => format!("{}", self.count),
+        }
+    }
+
     fn cost_str(&self) -> String {
+        if self.is_switchable() {
+            return "Free".into();
+        }
         match self.cost() {
             Some(cost) => cost.to_string_hum(),
             None => return "Complete".into(),
@@ -292,10 +669,9 @@ impl MenuItemData {
             name: format!("{}", i),
             description: format!("Menu item {}", i),
             count: 1,
-            cost_fn: Box::new(move |count| {
-                let cost: BigUint = BigUint::from(100 + i) * BigUint::from(count);
-                Some(cost.into())
-            }),
+            toggle: false,
+            machine: None,
+            cost_fn: Box::new(move |count| Some(GameUInt::from(100 + i) * count)),
             on_buy_fn: Box::new(move |count, state, parameters| {
                 parameters.knead_tick_size += 0.1;
             }),
@@ -307,8 +683,13 @@ impl MenuItemData {
 pub struct MenuItem {
     data: MenuItemData,
     sprite: Sprite,
-    state: VisibilityState,
     y_offset: BoundedPosition,
+    /// Where the item was last moved to, so it's only moved again when that changes
+    placed_x: f32,
+    /// Elapsed time the item was last bought at, while it's still bouncing
+    bounce_started: Option<f32>,
+    /// Can't be bought yet, see [MenuItemData::machine]
+    locked: bool,
     name_text: TextSprite,
     desc_text: TextSprite,
     cost_text: TextSprite,
@@ -320,33 +701,29 @@ pub struct MenuItem {
 impl MenuItem {
     const NAME_TEXT_OFFSET: f32 = -18.0;
     const COST_TEXT_OFFSET: f32 = 18.0;
-    pub fn new(data: MenuItemData, y_offset: BoundedPosition) -> Self {
+    const HIDDEN_X: f32 = -95.5;
+    const VISIBLE_X: f32 = 94.5;
+    const BOUNCE_SECS: f32 = 0.25;
+    /// How far the item hops to the right when bought
+    const BOUNCE_PX: f32 = 6.0;
+
+    pub fn new(data: MenuItemData, y_offset: BoundedPosition, sprites: &mut SpriteOwner) -> Self {
         let y = y_offset.get();
-        let mut sprite =
-            crate::helpers::load_sprite_at("res/menu_item_background0", -95.0, y, None);
+        let mut sprite = sprites.load_sprite_at("res/menu_item_background0", -95.0, y);
         sprite.set_z_index(10).unwrap();
-        let mut name_text = TextSprite::new(
-            "",
-            crankstart::graphics::LCDColor::Solid(crankstart_sys::LCDSolidColor::kColorWhite),
-        )
-        .unwrap();
+        let mut name_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorWhite));
         name_text
             .get_sprite_mut()
             .move_to(-95.0, y + Self::NAME_TEXT_OFFSET)
             .unwrap();
         name_text.get_sprite_mut().set_z_index(11).unwrap();
-        let mut desc_text = TextSprite::new(
-            "",
-            crankstart::graphics::LCDColor::Solid(crankstart_sys::LCDSolidColor::kColorWhite),
-        )
-        .unwrap();
+        let mut desc_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorWhite));
         desc_text.get_sprite_mut().move_to(-95.0, y).unwrap();
         desc_text.get_sprite_mut().set_z_index(11).unwrap();
-        let mut cost_text = TextSprite::new(
-            "",
-            crankstart::graphics::LCDColor::Solid(crankstart_sys::LCDSolidColor::kColorWhite),
-        )
-        .unwrap();
+        let mut cost_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorWhite));
         cost_text
             .get_sprite_mut()
             .move_to(-95.0, y + Self::COST_TEXT_OFFSET)
@@ -359,8 +736,10 @@ impl MenuItem {
         let mut t = Self {
             data,
             sprite,
-            state: VisibilityState::Hidden,
             y_offset,
+            placed_x: Self::HIDDEN_X,
+            bounce_started: None,
+            locked: false,
             name_text,
             desc_text,
             cost_text,
@@ -373,9 +752,12 @@ impl MenuItem {
     }
 
     fn update_text(&mut self) {
-        let name_str = format!("{}: {}", self.data.name, self.data.count);
+        let name_str = format!("{}: {}", self.data.name, self.data.count_str());
         self.name_text.update_text(name_str).unwrap();
-        let cost_str = format!("Cost: {}", self.data.cost_str());
+        let cost_str = match self.data.machine {
+            Some(machine) if self.locked => format!("Needs Machine {}", machine + 1),
+            _ => format!("Cost: {}", self.data.cost_str()),
+        };
         self.cost_text.update_text(cost_str).unwrap();
         self.desc_text.update_text(&self.data.description).unwrap();
     }
@@ -395,21 +777,31 @@ impl MenuItem {
         }
     }
 
+    fn set_locked(&mut self, locked: bool) {
+        if self.locked != locked {
+            self.locked = locked;
+            self.update_text();
+        }
+    }
+
     /// Press menu item. Returns true if successfully bought, false if not.
     pub fn press_and_trigger(
         &mut self,
         state: &mut CoreState,
         parameters: &mut CoreParameters,
     ) -> bool {
+        if self.locked {
+            return false;
+        }
         if let Some(cost) = self.data.cost() {
             System::log_to_console(&format!(
                 "Trying to buy: cost: {}, with cash {}",
                 cost.to_string_hum(),
                 state.money.to_string_hum()
             ));
-            if cost < state.money {
+            if let Some(remaining) = state.money.checked_sub(&cost) {
                 self.data.count += 1;
-                state.money.sub_assign(cost);
+                state.money = remaining;
                 self.data.on_buy(state, parameters);
                 self.update_text();
                 true
@@ -432,28 +824,33 @@ impl MenuItem {
         }
     }
 
-    fn scroll(&mut self, change: f32) {
-        self.y_offset.change(change);
-        let (x, _) = self.sprite.get_position().unwrap();
-        let y = self.y_offset.get();
-        self.sprite.move_to(x, y).unwrap();
-        self.name_text
-            .get_sprite_mut()
-            .move_to(94.5, y + Self::NAME_TEXT_OFFSET)
-            .unwrap();
-        self.desc_text.get_sprite_mut().move_to(94.5, y).unwrap();
-        self.cost_text
-            .get_sprite_mut()
-            .move_to(94.5, y + Self::COST_TEXT_OFFSET)
-            .unwrap();
+    fn start_bounce(&mut self) {
+        self.bounce_started = System::get().get_elapsed_time().ok();
     }
-    pub fn set_state(&mut self, state: VisibilityState) {
-        self.state = state;
-        let y = self.y_offset.get();
-        let x = match state {
-            VisibilityState::Hidden => -95.5,
-            VisibilityState::Visible => 94.5,
+
+    /// Offset for hopping out and back after it's bought
+    fn bounce_offset(&mut self, now: f32) -> f32 {
+        let started = match self.bounce_started {
+            Some(started) => started,
+            None => return 0.0,
         };
+        let t = ((now - started) / Self::BOUNCE_SECS).min(1.0);
+        if t >= 1.0 {
+            self.bounce_started = None;
+        }
+        Self::BOUNCE_PX * 4.0 * t * (1.0 - t)
+    }
+
+    fn update_position(&mut self, base_x: f32, now: f32) {
+        let x = base_x + self.bounce_offset(now);
+        if x != self.placed_x {
+            self.place(x);
+        }
+    }
+
+    fn place(&mut self, x: f32) {
+        self.placed_x = x;
+        let y = self.y_offset.get();
         self.sprite.move_to(x, y).unwrap();
         self.name_text
             .get_sprite_mut()
@@ -465,6 +862,11 @@ impl MenuItem {
             .move_to(x, y + Self::COST_TEXT_OFFSET)
             .unwrap();
     }
+
+    fn scroll(&mut self, change: f32) {
+        self.y_offset.change(change);
+        self.place(self.placed_x);
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,186 @@
+use alloc::vec::Vec;
+use crankstart::log_to_console;
+use crankstart::sound::{FilePlayer, Sound};
+use crankstart::system::System;
+
+/// Background music for each part of the game
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MusicTrack {
+    LevelSelect,
+    Kitchen,
+    /// Played while a random event is running
+    Event,
+}
+
+impl MusicTrack {
+    /// Files for each layer of the track, in the order they're brought in as the kitchen gets
+    /// busier. All layers of a track are the same length so they stay in time with each other
+    fn layer_files(&self) -> &'static [&'static str] {
+        match self {
+            MusicTrack::LevelSelect => &["res/audio/music/level_select.wav"],
+            MusicTrack::Kitchen => &[
+                "res/audio/music/kitchen_base.wav",
+                "res/audio/music/kitchen_rhythm.wav",
+                "res/audio/music/kitchen_melody.wav",
+            ],
+            MusicTrack::Event => &["res/audio/music/event.wav"],
+        }
+    }
+}
+
+/// Moves `value` towards `target` by at most `step`
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+#[derive(Debug)]
+struct Layer {
+    player: FilePlayer,
+    level: f32,
+}
+
+/// A track that's playing, or fading out
+#[derive(Debug)]
+struct TrackPlayback {
+    track: MusicTrack,
+    layers: Vec<Layer>,
+    /// Crossfade level, from 0.0 to 1.0
+    fade: f32,
+}
+
+impl TrackPlayback {
+    fn start(track: MusicTrack) -> Self {
+        let sound = Sound::get();
+        let layers = track
+            .layer_files()
+            .iter()
+            .filter_map(|path| {
+                let player = sound.get_file_player().ok()?;
+                let started = player
+                    .load_into_player(path)
+                    .and_then(|_| player.set_volume(0.0, 0.0))
+                    // Zero repeats loops forever
+                    .and_then(|_| player.play(0));
+                match started {
+                    Ok(_) => Some(Layer { player, level: 0.0 }),
+                    Err(e) => {
+                        log_to_console!("Failed to start music layer {}: {:?}", path, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self {
+            track,
+            layers,
+            fade: 0.0,
+        }
+    }
+
+    fn stop(&self) {
+        for layer in self.layers.iter() {
+            layer.player.stop().ok();
+        }
+    }
+
+    /// Fades each layer towards being on or off, and applies the volume
+    fn update(&mut self, layer_step: f32, active_layers: usize, volume: f32) {
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            let target = if i < active_layers { 1.0 } else { 0.0 };
+            layer.level = approach(layer.level, target, layer_step);
+            let layer_volume = volume * self.fade * layer.level;
+            layer.player.set_volume(layer_volume, layer_volume).ok();
+        }
+    }
+}
+
+/// Looping background music. Crossfades between tracks, brings in extra layers as the kitchen gets
+/// busier, and ducks under sound effects
+#[derive(Debug)]
+pub struct Music {
+    current: Option<TrackPlayback>,
+    fading_out: Option<TrackPlayback>,
+    /// Extra layers on top of the base layer
+    intensity: usize,
+    /// From 0.0 to 1.0, set from the settings
+    volume: f32,
+    duck: f32,
+    last_tick: f32,
+}
+
+impl Music {
+    const CROSSFADE_SECS: f32 = 1.5;
+    const LAYER_FADE_SECS: f32 = 2.0;
+    const DUCK_SECS: f32 = 0.2;
+    /// Music volume while a sound effect is playing
+    const DUCKED_LEVEL: f32 = 0.4;
+
+    pub fn new(volume: f32) -> Self {
+        Self {
+            current: None,
+            fading_out: None,
+            intensity: 0,
+            volume,
+            duck: 1.0,
+            last_tick: System::get().get_elapsed_time().unwrap_or(0.0),
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// Switches to `track`, crossfading from whatever is playing. Does nothing if it's already
+    /// playing
+    pub fn play(&mut self, track: MusicTrack) {
+        if self.current.as_ref().map(|current| current.track) == Some(track) {
+            return;
+        }
+        if let Some(fading_out) = self.fading_out.take() {
+            fading_out.stop();
+        }
+        self.fading_out = self.current.take();
+        self.current = Some(TrackPlayback::start(track));
+    }
+
+    /// How many layers to play on top of the base layer, for tracks that have them
+    pub fn set_intensity(&mut self, intensity: usize) {
+        self.intensity = intensity;
+    }
+
+    /// `ducked` should be true while sound effects are playing
+    pub fn update(&mut self, ducked: bool) {
+        let now = System::get().get_elapsed_time().unwrap_or(self.last_tick);
+        let dt = now - self.last_tick;
+        self.last_tick = now;
+
+        let duck_target = if ducked { Self::DUCKED_LEVEL } else { 1.0 };
+        self.duck = approach(self.duck, duck_target, dt / Self::DUCK_SECS);
+        let volume = self.volume * self.duck;
+        let fade_step = dt / Self::CROSSFADE_SECS;
+        let layer_step = dt / Self::LAYER_FADE_SECS;
+        let active_layers = 1 + self.intensity;
+
+        if let Some(current) = &mut self.current {
+            current.fade = approach(current.fade, 1.0, fade_step);
+            current.update(layer_step, active_layers, volume);
+        }
+        let faded_out = match &mut self.fading_out {
+            Some(fading_out) => {
+                fading_out.fade = approach(fading_out.fade, 0.0, fade_step);
+                fading_out.update(layer_step, active_layers, volume);
+                fading_out.fade <= 0.0
+            }
+            None => false,
+        };
+        if faded_out {
+            if let Some(fading_out) = self.fading_out.take() {
+                fading_out.stop();
+            }
+        }
+    }
+}
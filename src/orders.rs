@@ -0,0 +1,447 @@
+use crate::core_elements::{
+    CoreParameters, CoreState, PastaType, TextSpriteWithValue, VisibilityState,
+};
+use crate::events::RandomEvents;
+use crate::game_events::{GameEvent, GameEventBus};
+use crate::game_value::{GameDecimal, GameUInt, GameValue};
+use crate::helpers::Rng;
+use crate::sprite_owner::SpriteOwner;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crankstart::graphics::LCDColor;
+use crankstart::log_to_console;
+use crankstart::sprite::Sprite;
+use crankstart::system::System;
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons, PDRect};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Customer {
+    /// Small, quick orders
+    MarketStall,
+    /// Medium orders that pay well
+    Trattoria,
+    /// Large, slow orders that pay in diamonds too
+    Wedding,
+}
+
+impl Customer {
+    const ALL: [Customer; 3] = [
+        Customer::MarketStall,
+        Customer::Trattoria,
+        Customer::Wedding,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Customer::MarketStall => "Market",
+            Customer::Trattoria => "Trattoria",
+            Customer::Wedding => "Wedding",
+        }
+    }
+
+    /// Min and max quantity ordered
+    fn quantity_range(&self) -> (usize, usize) {
+        match self {
+            Customer::MarketStall => (2, 5),
+            Customer::Trattoria => (5, 12),
+            Customer::Wedding => (15, 30),
+        }
+    }
+
+    /// Seconds allowed per item ordered
+    fn secs_per_item(&self) -> f32 {
+        match self {
+            Customer::MarketStall => 10.0,
+            Customer::Trattoria => 12.0,
+            Customer::Wedding => 15.0,
+        }
+    }
+
+    /// Multiplier on the sale price of the pasta ordered
+    fn reward_multiplier(&self) -> GameDecimal {
+        match self {
+            Customer::MarketStall => GameDecimal::from_ratio(3, 2),
+            Customer::Trattoria => GameDecimal::from_whole(2),
+            Customer::Wedding => GameDecimal::from_whole(3),
+        }
+    }
+
+    fn diamonds(&self) -> usize {
+        match self {
+            Customer::MarketStall => 0,
+            Customer::Trattoria => 1,
+            Customer::Wedding => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Order {
+    customer: Customer,
+    pasta: PastaType,
+    quantity: usize,
+    reward: GameUInt,
+    /// Seconds until the customer gives up
+    remaining: f32,
+}
+
+impl Order {
+    fn random(rng: &mut Rng, parameters: &CoreParameters) -> Self {
+        let customer = Customer::ALL[rng.range_usize(0, Customer::ALL.len() - 1)];
+        let available = PastaType::available(parameters);
+        let pasta = available[rng.range_usize(0, available.len() - 1)];
+        let (min, max) = customer.quantity_range();
+        let quantity = rng.range_usize(min, max);
+        let reward = (parameters.sale_price_of(pasta)
+            * customer.reward_multiplier()
+            * GameDecimal::from_whole(quantity))
+        .floor();
+        Self {
+            customer,
+            pasta,
+            quantity,
+            reward,
+            remaining: quantity as f32 * customer.secs_per_item(),
+        }
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "{}: {} {}",
+            self.customer.name(),
+            self.quantity,
+            self.pasta.name(self.quantity)
+        )
+    }
+
+    fn details(&self) -> String {
+        let secs = self.remaining.max(0.0) as usize;
+        match self.customer.diamonds() {
+            0 => format!("{} ({}s)", self.reward.to_string_hum(), secs),
+            d => format!("{} +{}d ({}s)", self.reward.to_string_hum(), d, secs),
+        }
+    }
+}
+
+/// The saved part of the [OrderBoard]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrdersState {
+    rng: Rng,
+    orders: Vec<Order>,
+    /// Seconds until the next customer turns up
+    next_order_in: f32,
+}
+
+impl Default for OrdersState {
+    fn default() -> Self {
+        Self {
+            rng: Rng::from_time(),
+            orders: Vec::new(),
+            next_order_in: OrderBoard::MIN_GAP,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OrderSlot {
+    title: TextSpriteWithValue<String>,
+    details: TextSpriteWithValue<String>,
+}
+
+/// Board of customer orders that slides in from the right. Orders are filled from the pasta stock
+/// with A, and the last entry sells the whole stock at the normal sale price instead
+#[derive(Debug)]
+pub struct OrderBoard {
+    visibility: VisibilityState,
+    _sprites: SpriteOwner,
+    background: Sprite,
+    slots: Vec<OrderSlot>,
+    sell_text: TextSpriteWithValue<String>,
+    stock_text: TextSpriteWithValue<String>,
+    selected: usize,
+    state: OrdersState,
+}
+
+impl OrderBoard {
+    pub const MAX_ORDERS: usize = 3;
+    const MIN_GAP: f32 = 30.0;
+    const MAX_GAP: f32 = 90.0;
+    /// Pasta over this amount (of any one type) is sold straight away
+    const STOCK_CAPACITY: usize = 50;
+    const VISIBLE_X: f32 = 304.5;
+    const HIDDEN_X: f32 = 495.5;
+    const SLOT_Y_START: f32 = 30.0;
+    const SLOT_SPACING: f32 = 45.0;
+    const DETAILS_OFFSET: f32 = 16.0;
+
+    pub fn new(state: OrdersState) -> Self {
+        // The shop's background, mirrored as the board comes in from the other side
+        let mut sprites = SpriteOwner::new();
+        let mut background = sprites.load_sprite_at("res/menu_background", Self::HIDDEN_X, 95.5);
+        let image = background.get_image().unwrap().unwrap();
+        background
+            .set_image(image, LCDBitmapFlip::kBitmapFlippedX)
+            .unwrap();
+        background.set_z_index(9).unwrap();
+        let slots = (0..Self::MAX_ORDERS)
+            .map(|i| {
+                let y = Self::SLOT_Y_START + i as f32 * Self::SLOT_SPACING;
+                OrderSlot {
+                    title: board_text(y, &mut sprites),
+                    details: board_text(y + Self::DETAILS_OFFSET, &mut sprites),
+                }
+            })
+            .collect();
+        let sell_text = board_text(
+            Self::SLOT_Y_START + Self::MAX_ORDERS as f32 * Self::SLOT_SPACING,
+            &mut sprites,
+        );
+        let mut stock_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        stock_text.get_sprite_mut().move_to(284.0, 118.0).unwrap();
+        let stock_text =
+            TextSpriteWithValue::new(stock_text, String::new(), Box::new(String::clone));
+        let mut s = Self {
+            visibility: VisibilityState::Hidden,
+            _sprites: sprites,
+            background,
+            slots,
+            sell_text,
+            stock_text,
+            selected: 0,
+            state,
+        };
+        s.set_state(VisibilityState::Hidden);
+        s
+    }
+
+    pub fn to_saved(&self) -> OrdersState {
+        self.state.clone()
+    }
+
+    pub fn bounds(&self) -> PDRect {
+        self.background.get_bounds().unwrap()
+    }
+
+    pub fn is_visible(&self) -> bool {
+        matches!(self.visibility, VisibilityState::Visible)
+    }
+
+    /// Brings the board in with the first order the stock can fill selected, or the sell entry
+    /// if there isn't one, so A does something useful straight away
+    pub fn open(&mut self, state: &CoreState) {
+        self.selected = self
+            .state
+            .orders
+            .iter()
+            .position(|order| state.pasta_stock.get(order.pasta) >= order.quantity)
+            .unwrap_or(Self::MAX_ORDERS);
+        self.set_state(VisibilityState::Visible);
+    }
+
+    pub fn set_state(&mut self, visibility: VisibilityState) {
+        self.visibility = visibility;
+        let x = match visibility {
+            VisibilityState::Hidden => Self::HIDDEN_X,
+            VisibilityState::Visible => Self::VISIBLE_X,
+        };
+        self.background.move_to(x, 95.5).unwrap();
+        for slot in self.slots.iter_mut() {
+            move_text_x(&mut slot.title, x);
+            move_text_x(&mut slot.details, x);
+        }
+        move_text_x(&mut self.sell_text, x);
+    }
+
+    fn sell_price(
+        pasta: PastaType,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+    ) -> GameDecimal {
+        parameters.sale_price_of(pasta) * random_events.price_multiplier()
+    }
+
+    /// What the whole stock would sell for right now, including any event's price multiplier
+    fn stock_value(
+        state: &CoreState,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+    ) -> GameDecimal {
+        let mut value = GameDecimal::default();
+        for pasta in PastaType::ALL {
+            value += Self::sell_price(pasta, parameters, random_events)
+                * GameDecimal::from_whole(state.pasta_stock.get(pasta));
+        }
+        value
+    }
+
+    /// Sells all stock at the current sale price, which is what the sell entry shows
+    fn sell_all(
+        &mut self,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+        game_events: &mut GameEventBus,
+    ) {
+        let total = Self::stock_value(state, parameters, random_events);
+        if state.pasta_stock.take_all().is_empty() {
+            game_events.push(GameEvent::SaleDenied);
+            return;
+        }
+        game_events.push(GameEvent::MoneyGained {
+            amount: total.floor(),
+            at: text_position(&self.sell_text),
+        });
+        state.add_money_decimal(total);
+    }
+
+    fn sell_overflow(
+        &mut self,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+        game_events: &mut GameEventBus,
+    ) {
+        for pasta in PastaType::ALL {
+            let overflow = state
+                .pasta_stock
+                .get(pasta)
+                .saturating_sub(Self::STOCK_CAPACITY);
+            if overflow > 0 && state.pasta_stock.take(pasta, overflow) {
+                let price = Self::sell_price(pasta, parameters, random_events);
+                let amount = price * GameDecimal::from_whole(overflow);
+                game_events.push(GameEvent::MoneyGained {
+                    amount: amount.floor(),
+                    at: text_position(&self.stock_text),
+                });
+                state.add_money_decimal(amount);
+            }
+        }
+    }
+
+    fn deliver_selected(&mut self, state: &mut CoreState, game_events: &mut GameEventBus) {
+        let order = match self.state.orders.get(self.selected) {
+            Some(order) => order,
+            None => {
+                game_events.push(GameEvent::SaleDenied);
+                return;
+            }
+        };
+        if !state.pasta_stock.take(order.pasta, order.quantity) {
+            game_events.push(GameEvent::SaleDenied);
+            return;
+        }
+        log_to_console!("Delivered order {:?}", order);
+        let order = self.state.orders.remove(self.selected);
+        game_events.push(GameEvent::MoneyGained {
+            amount: order.reward.clone(),
+            at: text_position(&self.slots[self.selected].title),
+        });
+        state.add_money_big(order.reward);
+        state.diamonds += order.customer.diamonds();
+    }
+
+    fn update_orders(&mut self, dt: f32, parameters: &CoreParameters) {
+        for order in self.state.orders.iter_mut() {
+            order.remaining -= dt;
+        }
+        self.state.orders.retain(|order| order.remaining > 0.0);
+
+        if self.state.orders.len() < Self::MAX_ORDERS {
+            self.state.next_order_in -= dt;
+            if self.state.next_order_in <= 0.0 {
+                let order = Order::random(&mut self.state.rng, parameters);
+                self.state.orders.push(order);
+                let next_order_in = self.state.rng.range_f32(Self::MIN_GAP, Self::MAX_GAP);
+                self.state.next_order_in = next_order_in;
+            }
+        }
+    }
+
+    fn update_text(
+        &mut self,
+        state: &CoreState,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+    ) {
+        // Index MAX_ORDERS is the sell entry
+        let selected = self.selected;
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let marker = if i == selected { "> " } else { "" };
+            let (title, details) = match self.state.orders.get(i) {
+                Some(order) => (format!("{}{}", marker, order.title()), order.details()),
+                None => (format!("{}Waiting...", marker), String::new()),
+            };
+            slot.title.update_value(&title);
+            slot.details.update_value(&details);
+        }
+        let marker = if selected == Self::MAX_ORDERS {
+            "> "
+        } else {
+            ""
+        };
+        let stock = state.pasta_stock.total();
+        let value = Self::stock_value(state, parameters, random_events).floor();
+        let sell_str = format!("{}Sell all: {}", marker, value.to_string_hum());
+        self.sell_text.update_value(&sell_str);
+        let stock_str = match stock {
+            0 => String::new(),
+            _ => format!("Stock x{}", stock),
+        };
+        self.stock_text.update_value(&stock_str);
+    }
+
+    /// Counts down the current orders and the wait for the next one
+    pub fn step(&mut self, dt: f32, parameters: &CoreParameters) {
+        self.update_orders(dt, parameters);
+    }
+
+    pub fn update(
+        &mut self,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        random_events: &RandomEvents,
+        game_events: &mut GameEventBus,
+    ) {
+        self.sell_overflow(state, parameters, random_events, game_events);
+
+        if self.is_visible() {
+            let (_, pressed, _) = System::get().get_button_state().unwrap();
+            if (pressed & PDButtons::kButtonUp).0 != 0 {
+                self.selected = self.selected.saturating_sub(1);
+            } else if (pressed & PDButtons::kButtonDown).0 != 0 {
+                self.selected = (self.selected + 1).min(Self::MAX_ORDERS);
+            }
+            if (pressed & PDButtons::kButtonA).0 != 0 {
+                if self.selected == Self::MAX_ORDERS {
+                    self.sell_all(state, parameters, random_events, game_events);
+                } else {
+                    self.deliver_selected(state, game_events);
+                }
+            }
+        }
+        self.update_text(state, parameters, random_events);
+    }
+}
+
+fn board_text(y: f32, sprites: &mut SpriteOwner) -> TextSpriteWithValue<String> {
+    let mut text = sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorWhite));
+    text.get_sprite_mut()
+        .move_to(OrderBoard::HIDDEN_X, y)
+        .unwrap();
+    text.get_sprite_mut().set_z_index(11).unwrap();
+    TextSpriteWithValue::new(text, String::new(), Box::new(String::clone))
+}
+
+fn text_position(text: &TextSpriteWithValue<String>) -> (f32, f32) {
+    text.sprite.get_sprite().get_position().unwrap()
+}
+
+fn move_text_x(text: &mut TextSpriteWithValue<String>, x: f32) {
+    let sprite = text.sprite.get_sprite_mut();
+    let (_, y) = sprite.get_position().unwrap();
+    sprite.move_to(x, y).unwrap();
+}
@@ -0,0 +1,371 @@
+use crate::core_elements::{AutoTicker, CoreParameters, CoreState, PastaType, TextSpriteWithValue};
+use crate::game_value::{GameUInt, GameValue};
+use crate::sprite_owner::SpriteOwner;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use crankstart::graphics::{Bitmap, Graphics, LCDColor};
+use crankstart::sprite::Sprite;
+use crankstart::system::System;
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons};
+use serde::{Deserialize, Serialize};
+
+/// How far along a piece of pasta on the rack is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DryingStage {
+    /// Not dry yet, taking it off now sells it as fresh
+    Fresh,
+    Dried,
+    /// Left too long, it's gone brittle and is worth less than fresh
+    OverDried,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DryingPasta {
+    pasta: PastaType,
+    /// Seconds spent on the rack so far
+    hung_for: f32,
+}
+
+impl DryingPasta {
+    fn stage(&self) -> DryingStage {
+        if self.hung_for >= DryingRack::OVER_DRIED_SECS {
+            DryingStage::OverDried
+        } else if self.hung_for >= DryingRack::DRYING_SECS {
+            DryingStage::Dried
+        } else {
+            DryingStage::Fresh
+        }
+    }
+
+    /// What this becomes when taken off the rack at its current stage
+    fn taken_off(&self) -> PastaType {
+        let changed = match self.stage() {
+            DryingStage::Fresh => None,
+            DryingStage::Dried => self.pasta.dried(),
+            DryingStage::OverDried => self.pasta.over_dried(),
+        };
+        changed.unwrap_or(self.pasta)
+    }
+}
+
+/// Finished pasta hanging up to dry. Dried pasta is worth more, but has to be taken off the rack
+/// before it goes brittle. Times are kept as seconds hung rather than absolute times, as elapsed
+/// time restarts with the game
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DryingRack {
+    hanging: Vec<DryingPasta>,
+}
+
+impl DryingRack {
+    const BASE_SLOTS: usize = 4;
+    const SLOTS_PER_LEVEL: usize = 2;
+    /// Highest rack slot level that can be bought
+    pub const MAX_SLOT_LEVEL: usize = 4;
+    pub const MAX_SLOTS: usize = Self::BASE_SLOTS + Self::SLOTS_PER_LEVEL * Self::MAX_SLOT_LEVEL;
+    /// Seconds until pasta is dried
+    const DRYING_SECS: f32 = 60.0;
+    /// Seconds until pasta has been left too long and goes brittle
+    const OVER_DRIED_SECS: f32 = 180.0;
+
+    pub fn capacity(parameters: &CoreParameters) -> usize {
+        if parameters.has_drying_rack {
+            let level = parameters.rack_slot_level.min(Self::MAX_SLOT_LEVEL);
+            Self::BASE_SLOTS + level * Self::SLOTS_PER_LEVEL
+        } else {
+            0
+        }
+    }
+
+    /// Returns false if the rack is full or the pasta can't be dried
+    pub fn try_hang(&mut self, pasta: PastaType, capacity: usize) -> bool {
+        if self.hanging.len() >= capacity || pasta.dried().is_none() {
+            return false;
+        }
+        self.hanging.push(DryingPasta {
+            pasta,
+            hung_for: 0.0,
+        });
+        true
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        for drying in self.hanging.iter_mut() {
+            drying.hung_for += dt;
+        }
+    }
+
+    /// Advances by a long stretch of time in one go. With a rack hand, anything that dries during
+    /// it is taken off before it can go brittle, and returned
+    pub fn fast_forward(&mut self, secs: f32, has_rack_hand: bool) -> Vec<PastaType> {
+        self.advance(secs);
+        if !has_rack_hand {
+            return Vec::new();
+        }
+        let mut collected = Vec::new();
+        self.hanging.retain(|drying| {
+            if drying.stage() == DryingStage::Fresh {
+                return true;
+            }
+            collected.extend(drying.pasta.dried());
+            false
+        });
+        collected
+    }
+
+    /// Takes everything that has reached `stage` or beyond off the rack, returning what it became
+    fn take_from(&mut self, stage: DryingStage) -> Vec<PastaType> {
+        let mut taken = Vec::new();
+        self.hanging.retain(|drying| {
+            let drying_stage = drying.stage();
+            let take = match stage {
+                DryingStage::Fresh => true,
+                DryingStage::Dried => drying_stage != DryingStage::Fresh,
+                DryingStage::OverDried => drying_stage == DryingStage::OverDried,
+            };
+            if take {
+                taken.push(drying.taken_off());
+            }
+            !take
+        });
+        taken
+    }
+
+    /// Takes all pasta off the rack, whatever state it's in
+    pub fn take_all(&mut self) -> Vec<PastaType> {
+        self.take_from(DryingStage::Fresh)
+    }
+
+    /// Takes off only the pasta that has finished drying
+    pub fn take_dried(&mut self) -> Vec<PastaType> {
+        self.take_from(DryingStage::Dried)
+    }
+}
+
+/// A sprite with a count next to it, for one of the production stage buffers
+#[derive(Debug)]
+struct StageCounter {
+    icon: Sprite,
+    text: TextSpriteWithValue<GameUInt>,
+    visible: bool,
+}
+
+impl StageCounter {
+    fn new(image_path: &str, x: f32, y: f32, sprites: &mut SpriteOwner) -> Self {
+        let mut icon = sprites.load_sprite_at(image_path, x, y);
+        icon.set_visible(false).unwrap();
+        let mut text = sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        text.get_sprite_mut().move_to(x + 30.0, y).unwrap();
+        text.get_sprite_mut().set_visible(false).unwrap();
+        let text = TextSpriteWithValue::new(
+            text,
+            GameUInt::default(),
+            Box::new(|count| format!("x{}", GameUInt::to_string_hum(count))),
+        );
+        Self {
+            icon,
+            text,
+            visible: false,
+        }
+    }
+
+    fn update(&mut self, visible: bool, count: &GameUInt) {
+        if self.visible != visible {
+            self.icon.set_visible(visible).unwrap();
+            self.text
+                .sprite
+                .get_sprite_mut()
+                .set_visible(visible)
+                .unwrap();
+            self.visible = visible;
+        }
+        self.text.update_value(count);
+    }
+}
+
+/// One place on the rack, with the stage it's showing so its sprite is only touched on a change
+#[derive(Debug)]
+struct RackSlot {
+    sprite: Sprite,
+    /// None while the slot is hidden
+    shown: Option<DryingStage>,
+}
+
+#[derive(Debug)]
+struct RackVisuals {
+    frame: Sprite,
+    slots: Vec<RackSlot>,
+    fresh_image: Bitmap,
+    dried_image: Bitmap,
+    brittle_image: Bitmap,
+    pos: (f32, f32),
+    capacity: usize,
+}
+
+impl RackVisuals {
+    const SLOT_SPACING: f32 = 12.0;
+    /// Width of the rack that slots are spread across, they squeeze together once it's full
+    const RACK_WIDTH: f32 = 56.0;
+
+    fn new(x: f32, y: f32, sprites: &mut SpriteOwner) -> Self {
+        let mut frame = sprites.load_sprite_at("res/drying_rack", x, y);
+        frame.set_visible(false).unwrap();
+        let graphics = Graphics::get();
+        let fresh_image = graphics.load_bitmap("res/rack_sheet").unwrap();
+        let dried_image = graphics.load_bitmap("res/rack_sheet_dried").unwrap();
+        let brittle_image = graphics.load_bitmap("res/rack_sheet_brittle").unwrap();
+        let slots = (0..DryingRack::MAX_SLOTS)
+            .map(|_| {
+                let mut sprite = sprites.new_sprite();
+                sprite
+                    .set_image(fresh_image.clone(), LCDBitmapFlip::kBitmapUnflipped)
+                    .unwrap();
+                sprite.set_visible(false).unwrap();
+                RackSlot {
+                    sprite,
+                    shown: None,
+                }
+            })
+            .collect();
+        Self {
+            frame,
+            slots,
+            fresh_image,
+            dried_image,
+            brittle_image,
+            pos: (x, y),
+            capacity: 0,
+        }
+    }
+
+    fn layout(&mut self, capacity: usize) {
+        self.frame.set_visible(capacity > 0).unwrap();
+        let spacing = if capacity > 1 {
+            Self::SLOT_SPACING.min(Self::RACK_WIDTH / (capacity - 1) as f32)
+        } else {
+            0.0
+        };
+        for (i, slot) in self.slots.iter_mut().take(capacity).enumerate() {
+            let centred_i = i as f32 - (capacity as f32 - 1.0) / 2.0;
+            slot.sprite
+                .move_to(self.pos.0 + centred_i * spacing, self.pos.1 - 6.0)
+                .unwrap();
+        }
+        self.capacity = capacity;
+    }
+
+    fn update(&mut self, capacity: usize, rack: &DryingRack) {
+        if self.capacity != capacity {
+            self.layout(capacity);
+        }
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let stage = match rack.hanging.get(i) {
+                Some(drying) if i < capacity => Some(drying.stage()),
+                _ => None,
+            };
+            if stage == slot.shown {
+                continue;
+            }
+            if let Some(stage) = stage {
+                let image = match stage {
+                    DryingStage::Fresh => &self.fresh_image,
+                    DryingStage::Dried => &self.dried_image,
+                    DryingStage::OverDried => &self.brittle_image,
+                };
+                slot.sprite
+                    .set_image(image.clone(), LCDBitmapFlip::kBitmapUnflipped)
+                    .unwrap();
+            }
+            slot.sprite.set_visible(stage.is_some()).unwrap();
+            slot.shown = stage;
+        }
+    }
+}
+
+/// The optional stages either side of kneading and rolling: flour supply and egg cracking before,
+/// and drying after. Each stage can be done by hand or automated from the shop
+#[derive(Debug)]
+pub struct ProductionChain {
+    _sprites: SpriteOwner,
+    flour: StageCounter,
+    eggs: StageCounter,
+    rack: RackVisuals,
+    flour_mill: AutoTicker,
+    egg_cracker: AutoTicker,
+    flour_progress: f32,
+    egg_progress: f32,
+}
+
+impl ProductionChain {
+    pub fn new() -> Self {
+        let mut sprites = SpriteOwner::new();
+        let flour = StageCounter::new("res/flour_sack", 20.0, 18.0, &mut sprites);
+        let eggs = StageCounter::new("res/egg", 20.0, 46.0, &mut sprites);
+        let rack = RackVisuals::new(205.0, 150.0, &mut sprites);
+        Self {
+            _sprites: sprites,
+            flour,
+            eggs,
+            rack,
+            flour_mill: AutoTicker::new(0.5),
+            egg_cracker: AutoTicker::new(0.25),
+            flour_progress: 0.0,
+            egg_progress: 0.0,
+        }
+    }
+
+    /// Adds the whole part of `progress` to `value`, keeping the fraction for next time
+    fn add_whole(progress: &mut f32, value: &mut GameUInt) {
+        let whole = *progress as usize;
+        if whole > 0 {
+            *value += whole;
+            *progress -= whole as f32;
+        }
+    }
+
+    fn collect(state: &mut CoreState, taken: Vec<PastaType>) {
+        for pasta in taken {
+            state.pasta_stock.add(pasta, 1);
+        }
+    }
+
+    /// Runs the flour mill, egg cracker and drying rack for `dt` seconds
+    pub fn step(&mut self, dt: f32, state: &mut CoreState, parameters: &CoreParameters) {
+        if parameters.egg_pasta {
+            self.flour_progress += self.flour_mill.poll(parameters.flour_mill_level, dt);
+            Self::add_whole(&mut self.flour_progress, &mut state.flour);
+            self.egg_progress += self.egg_cracker.poll(parameters.egg_cracker_level, dt);
+            Self::add_whole(&mut self.egg_progress, &mut state.eggs);
+        }
+        state.drying_rack.advance(dt);
+        if parameters.has_rack_hand {
+            let dried = state.drying_rack.take_dried();
+            Self::collect(state, dried);
+        }
+    }
+
+    /// `input_enabled` is false while another panel (e.g. the shop) is taking button presses
+    pub fn update(
+        &mut self,
+        state: &mut CoreState,
+        parameters: &CoreParameters,
+        input_enabled: bool,
+    ) {
+        if input_enabled {
+            let (_, pressed, _) = System::get().get_button_state().unwrap();
+            if parameters.egg_pasta && (pressed & PDButtons::kButtonUp).0 != 0 {
+                state.eggs += 1;
+            }
+            // Clearing the rack by hand takes everything, so fresh pasta can be sold straight away
+            if parameters.has_drying_rack && (pressed & PDButtons::kButtonDown).0 != 0 {
+                let taken = state.drying_rack.take_all();
+                Self::collect(state, taken);
+            }
+        }
+
+        self.flour.update(parameters.egg_pasta, &state.flour);
+        self.eggs.update(parameters.egg_pasta, &state.eggs);
+        self.rack
+            .update(DryingRack::capacity(parameters), &state.drying_rack);
+    }
+}
@@ -1,64 +1,132 @@
-use crate::core_elements::{CoreParameters, CoreState};
+use crate::core_elements::{CoreParameters, CoreState, PastaType};
+use crate::events::EventsState;
+use crate::helpers;
+use crate::hints::{Hint, HintsState};
+use crate::machine::{MachineProgress, MAX_MACHINES};
+use crate::orders::{OrderBoard, OrdersState};
+use crate::production::DryingRack;
+use crate::tutorial::TutorialStep;
 use crate::GameState;
 use alloc::format;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 use anyhow::{anyhow, Error};
 use crankstart::file::FileSystem;
 use crankstart::log_to_console;
 use crankstart_sys::FileOptions;
 use serde::{Deserialize, Serialize};
-use serde_json_core::heapless;
 
 fn save_filename(idx: usize) -> String {
     format!("PastaCranker-savefile.{}.json", idx)
 }
 
-/// This is picked randomly, but should be large enough to hold the save state. The BigUint values
-/// are not a static size so we can't know for certain!
-const FILE_BUFFER_SIZE: usize = 1024;
+/// Room for one big number. A GameUInt is saved as a list of u32 digits, so this is enough for
+/// values of around 300 decimal digits
+const BIG_NUMBER_BYTES: usize = 384;
+/// Big numbers outside of any list: money, diamonds, dough, flour, eggs, prices and so on
+const BIG_NUMBERS: usize = 10;
+/// Everything else outside of a list, field names included, with room for new fields
+const FIXED_BYTES: usize = 4096;
+const ORDER_BYTES: usize = 128 + BIG_NUMBER_BYTES;
+/// A machine's upgrades in the parameters plus its progress in the work in progress
+const MACHINE_BYTES: usize = 256;
+const RACK_SLOT_BYTES: usize = 48;
+/// The shop has fewer items than this, each saved as a count
+const MAX_MENU_ITEMS: usize = 64;
+/// Longest a usize can be written, plus a separator
+const COUNT_BYTES: usize = 21;
+const HINT_BYTES: usize = 24;
+
+/// Worst-case size of a save. The GameUInt values are not a static size so we can't know for
+/// certain, but saving checks for a save that doesn't fit rather than panicking
+const FILE_BUFFER_SIZE: usize = FIXED_BYTES
+    + BIG_NUMBERS * BIG_NUMBER_BYTES
+    + OrderBoard::MAX_ORDERS * ORDER_BYTES
+    + MAX_MACHINES * MACHINE_BYTES
+    + DryingRack::MAX_SLOTS * RACK_SLOT_BYTES
+    + (MAX_MENU_ITEMS + PastaType::ALL.len()) * COUNT_BYTES
+    + Hint::ALL.len() * HINT_BYTES;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct SaveState {
-    parameters: CoreParameters,
-    state: CoreState,
-    menu_counts: Vec<usize>,
+pub struct SaveState {
+    pub parameters: CoreParameters,
+    pub state: CoreState,
+    pub menu_counts: Vec<usize>,
+    #[serde(default)]
+    pub events: EventsState,
+    #[serde(default)]
+    pub orders: OrdersState,
+    /// Wall clock seconds when this was saved, used to catch up on time spent away. Zero for saves
+    /// from before this was recorded
+    #[serde(default)]
+    pub saved_at: usize,
+    #[serde(default)]
+    pub work_in_progress: WorkInProgress,
+    #[serde(default)]
+    pub tutorial: TutorialStep,
+    #[serde(default)]
+    pub hints: HintsState,
 }
 
-pub fn save_state(idx: usize, state: &GameState) {
-    let mut fs = FileSystem::get();
+/// Partly done work, so a half-kneaded dough ball or a half-rolled sheet picks up where it left
+/// off after a reload
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkInProgress {
+    pub knead_fill_pct: f32,
+    pub machines: Vec<MachineProgress>,
+}
+
+impl SaveState {
+    /// Seconds since this was saved, if known
+    pub fn offline_secs(&self) -> Option<usize> {
+        if self.saved_at == 0 {
+            return None;
+        }
+        Some(helpers::seconds_since_epoch().saturating_sub(self.saved_at))
+    }
+}
+
+/// Leaves the previous save in place if this one can't be written
+pub fn save_state(idx: usize, state: &GameState) -> Result<(), Error> {
     let save_state = SaveState {
         parameters: state.parameters.clone(),
         state: state.state.clone(),
         menu_counts: state.menu.to_counts(),
+        events: state.random_events.to_saved(),
+        orders: state.order_board.to_saved(),
+        saved_at: helpers::seconds_since_epoch(),
+        work_in_progress: WorkInProgress {
+            knead_fill_pct: state.flour_pile.fill_pct(),
+            machines: state.pasta_machine.to_saved(),
+        },
+        tutorial: state.tutorial.to_saved(),
+        hints: state.hints.to_saved(),
     };
-    let result: heapless::String<FILE_BUFFER_SIZE> =
-        serde_json_core::ser::to_string(&save_state).unwrap();
-    log_to_console!("result: {}", result);
-    let buf = result.as_bytes();
-    let mut file = fs
-        .open(&save_filename(idx), FileOptions::kFileWrite)
-        .unwrap();
-    let num_bytes_written = file.write(&buf).unwrap();
+    // Serialised before the file is opened, as opening it for writing empties it. Kept on the heap
+    // as it's too big for the stack
+    let mut buf = vec![0u8; FILE_BUFFER_SIZE];
+    let len = serde_json_core::ser::to_slice(&save_state, &mut buf)
+        .map_err(|e| anyhow!("Serde-error serialising: {}", e))?;
+    let mut fs = FileSystem::get();
+    let mut file = fs.open(&save_filename(idx), FileOptions::kFileWrite)?;
+    let num_bytes_written = file.write(&buf[..len])?;
     log_to_console!("num_bytes_written: {}", num_bytes_written);
-    file.flush().unwrap();
+    file.flush()?;
+    Ok(())
 }
 
-pub fn load_state(idx: usize) -> Result<(CoreState, CoreParameters, Vec<usize>), Error> {
+pub fn load_state(idx: usize) -> Result<SaveState, Error> {
     let mut fs = FileSystem::get();
     let mut file = fs.open(&save_filename(idx), FileOptions::kFileReadData)?;
-    let mut buf = [0u8; FILE_BUFFER_SIZE];
+    let mut buf = vec![0u8; FILE_BUFFER_SIZE];
     let bytes_read = file.read(&mut buf)?;
     log_to_console!("bytes_read: {}", bytes_read);
     let (save_state, bytes_parsed): (SaveState, usize) =
         serde_json_core::de::from_slice(&buf[..bytes_read])
             .map_err(|e| anyhow!("Serde-error deserialising: {}", e))?;
     log_to_console!("bytes_parsed: {}", bytes_parsed);
-    Ok((
-        save_state.state,
-        save_state.parameters,
-        save_state.menu_counts,
-    ))
+    Ok(save_state)
 }
 
 pub fn load_all_partial() -> Vec<Option<(CoreState, CoreParameters)>> {
@@ -66,7 +134,7 @@ pub fn load_all_partial() -> Vec<Option<(CoreState, CoreParameters)>> {
     let mut result = Vec::with_capacity(size);
     for i in 0..size {
         match load_state(i) {
-            Ok((state, parameters, _)) => result.push(Some((state, parameters))),
+            Ok(save_state) => result.push(Some((save_state.state, save_state.parameters))),
             Err(e) => {
                 log_to_console!("Error loading save {}: {:?}", i, e);
                 result.push(None);
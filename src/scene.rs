@@ -0,0 +1,124 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::Error;
+use core::fmt::Debug;
+use crankstart::log_to_console;
+use crankstart::sprite::Sprite;
+use crankstart_sys::PDRect;
+
+/// What a scene wants to happen to the stack after its update
+#[derive(Debug)]
+pub enum SceneTransition {
+    /// Stay on the current scene
+    None,
+    /// Open a scene over this one, e.g. a modal panel. This scene keeps its state and carries on
+    /// when the new one is popped
+    Push(Box<dyn Scene>),
+    /// Close this scene and go back to the one underneath
+    Pop,
+    /// Swap this scene for another, dropping it along with its sprites
+    Replace(Box<dyn Scene>),
+}
+
+/// One screen of the game. Only the scene on top of the stack is updated, so anything underneath
+/// is paused until it's uncovered
+pub trait Scene: Debug {
+    /// Called whenever the scene becomes the top of the stack, both when first shown and when a
+    /// scene over it is popped
+    fn enter(&mut self) {}
+
+    /// Called whenever the scene stops being the top of the stack, either covered or closed
+    fn exit(&mut self) {}
+
+    fn update(&mut self) -> Result<SceneTransition, Error>;
+
+    /// Per-sprite updates, for sprites that have a tag
+    fn update_sprite(&mut self, _sprite: &mut Sprite) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Only needs to implement drawing for sprites that set "use_custom_draw"
+    fn draw_sprite(
+        &self,
+        _sprite: &Sprite,
+        _bounds: &PDRect,
+        _draw_rect: &PDRect,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// The scenes currently open, with the active one on top
+#[derive(Debug)]
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(mut root: Box<dyn Scene>) -> Self {
+        root.enter();
+        Self { scenes: vec![root] }
+    }
+
+    fn active(&self) -> &dyn Scene {
+        self.scenes
+            .last()
+            .expect("Scene stack is never empty")
+            .as_ref()
+    }
+
+    fn active_mut(&mut self) -> &mut dyn Scene {
+        self.scenes
+            .last_mut()
+            .expect("Scene stack is never empty")
+            .as_mut()
+    }
+
+    fn apply(&mut self, transition: SceneTransition) {
+        match transition {
+            SceneTransition::None => {}
+            SceneTransition::Push(mut scene) => {
+                self.active_mut().exit();
+                scene.enter();
+                self.scenes.push(scene);
+            }
+            SceneTransition::Pop => {
+                if self.scenes.len() == 1 {
+                    log_to_console!("Can't pop the last scene");
+                    return;
+                }
+                if let Some(mut scene) = self.scenes.pop() {
+                    scene.exit();
+                }
+                self.active_mut().enter();
+            }
+            SceneTransition::Replace(mut scene) => {
+                if let Some(mut old) = self.scenes.pop() {
+                    old.exit();
+                }
+                scene.enter();
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    pub fn update(&mut self) -> Result<(), Error> {
+        let transition = self.active_mut().update()?;
+        self.apply(transition);
+        Ok(())
+    }
+
+    pub fn update_sprite(&mut self, sprite: &mut Sprite) -> Result<(), Error> {
+        self.active_mut().update_sprite(sprite)
+    }
+
+    pub fn draw_sprite(
+        &self,
+        sprite: &Sprite,
+        bounds: &PDRect,
+        draw_rect: &PDRect,
+    ) -> Result<(), Error> {
+        self.active().draw_sprite(sprite, bounds, draw_rect)
+    }
+}
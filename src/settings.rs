@@ -0,0 +1,400 @@
+use crate::core_elements::TextSpriteWithValue;
+use crate::game_value::NumberFormat;
+use crate::music::Music;
+use crate::scene::{Scene, SceneTransition};
+use crate::sprite_owner::SpriteOwner;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Error};
+use core::cell::RefCell;
+use crankstart::display::Display;
+use crankstart::file::FileSystem;
+use crankstart::graphics::LCDColor;
+use crankstart::log_to_console;
+use crankstart::sprite::Sprite;
+use crankstart::system::System;
+use crankstart_sys::{FileOptions, LCDSolidColor, PDButtons};
+use serde::{Deserialize, Serialize};
+use serde_json_core::heapless;
+
+/// Settings are shared between all save slots, so live in their own file
+const SETTINGS_FILENAME: &str = "settings.json";
+const FILE_BUFFER_SIZE: usize = 512;
+
+/// Which way the crank has to be turned to roll pasta
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrankDirection {
+    Forward,
+    Backward,
+    Either,
+}
+
+impl CrankDirection {
+    const ALL: [CrankDirection; 3] = [
+        CrankDirection::Forward,
+        CrankDirection::Backward,
+        CrankDirection::Either,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            CrankDirection::Forward => "Forward",
+            CrankDirection::Backward => "Backward",
+            CrankDirection::Either => "Either way",
+        }
+    }
+
+    /// Maps a raw crank change to forward progress for the machine
+    pub fn apply(&self, crank_change: f32) -> f32 {
+        match self {
+            CrankDirection::Forward => crank_change,
+            CrankDirection::Backward => -crank_change,
+            CrankDirection::Either => crank_change.abs(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefreshRate {
+    Fps20,
+    Fps30,
+    Fps50,
+}
+
+impl RefreshRate {
+    const ALL: [RefreshRate; 3] = [RefreshRate::Fps20, RefreshRate::Fps30, RefreshRate::Fps50];
+
+    fn fps(&self) -> f32 {
+        match self {
+            RefreshRate::Fps20 => 20.0,
+            RefreshRate::Fps30 => 30.0,
+            RefreshRate::Fps50 => 50.0,
+        }
+    }
+}
+
+/// Player preferences, independent of any save slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Out of [Settings::MAX_VOLUME]
+    pub master_volume: u8,
+    /// Out of [Settings::MAX_VOLUME], scaled by the master volume
+    pub sfx_volume: u8,
+    /// Out of [Settings::MAX_VOLUME], scaled by the master volume
+    pub music_volume: u8,
+    pub number_format: NumberFormat,
+    pub crank_direction: CrankDirection,
+    pub refresh_rate: RefreshRate,
+    pub inverted_display: bool,
+    pub show_tips: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: Self::MAX_VOLUME,
+            sfx_volume: Self::MAX_VOLUME,
+            music_volume: Self::MAX_VOLUME,
+            number_format: NumberFormat::Si,
+            crank_direction: CrankDirection::Forward,
+            refresh_rate: RefreshRate::Fps20,
+            inverted_display: false,
+            show_tips: true,
+        }
+    }
+}
+
+impl Settings {
+    pub const MAX_VOLUME: u8 = 10;
+
+    /// Loads the settings file, falling back to defaults if there isn't one yet
+    pub fn load() -> Self {
+        match Self::load_file() {
+            Ok(settings) => settings,
+            Err(e) => {
+                log_to_console!("Failed to load settings, using defaults. Error: {:?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn load_file() -> Result<Self, Error> {
+        let mut fs = FileSystem::get();
+        let mut file = fs.open(SETTINGS_FILENAME, FileOptions::kFileReadData)?;
+        let mut buf = [0u8; FILE_BUFFER_SIZE];
+        let bytes_read = file.read(&mut buf)?;
+        let (settings, _): (Settings, usize) = serde_json_core::de::from_slice(&buf[..bytes_read])
+            .map_err(|e| anyhow!("Serde-error deserialising: {}", e))?;
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        // Serialised before the file is opened, as opening it for writing empties it
+        let result: heapless::String<FILE_BUFFER_SIZE> = serde_json_core::ser::to_string(self)
+            .map_err(|e| anyhow!("Serde-error serialising: {}", e))?;
+        let mut fs = FileSystem::get();
+        let mut file = fs.open(SETTINGS_FILENAME, FileOptions::kFileWrite)?;
+        file.write(result.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Applies the settings that aren't owned by any one part of the game
+    pub fn apply(&self) -> Result<(), Error> {
+        self.number_format.set_current();
+        let display = Display::get();
+        display.set_refresh_rate(self.refresh_rate.fps())?;
+        display.set_inverted(self.inverted_display)?;
+        Ok(())
+    }
+
+    /// Scales `volume` by the master volume, giving 0.0 to 1.0
+    fn mixed_volume(&self, volume: u8) -> f32 {
+        let max = Self::MAX_VOLUME as f32;
+        (self.master_volume as f32 / max) * (volume as f32 / max)
+    }
+
+    /// Combined master and sfx volume, from 0.0 to 1.0
+    pub fn sfx_volume(&self) -> f32 {
+        self.mixed_volume(self.sfx_volume)
+    }
+
+    /// Combined master and music volume, from 0.0 to 1.0
+    pub fn music_volume(&self) -> f32 {
+        self.mixed_volume(self.music_volume)
+    }
+}
+
+/// Steps `current` through `options` by `change`, wrapping at either end
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, change: i32) -> T {
+    let idx = options.iter().position(|o| *o == current).unwrap_or(0) as i32;
+    let len = options.len() as i32;
+    options[(idx + change).rem_euclid(len) as usize]
+}
+
+#[derive(Debug, Copy, Clone)]
+enum SettingRow {
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    NumberFormat,
+    CrankDirection,
+    RefreshRate,
+    InvertedDisplay,
+    ShowTips,
+}
+
+impl SettingRow {
+    const ALL: [SettingRow; 8] = [
+        SettingRow::MasterVolume,
+        SettingRow::SfxVolume,
+        SettingRow::MusicVolume,
+        SettingRow::NumberFormat,
+        SettingRow::CrankDirection,
+        SettingRow::RefreshRate,
+        SettingRow::InvertedDisplay,
+        SettingRow::ShowTips,
+    ];
+
+    fn text(&self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        match self {
+            SettingRow::MasterVolume => format!("Volume: {}", settings.master_volume),
+            SettingRow::SfxVolume => format!("Sound effects: {}", settings.sfx_volume),
+            SettingRow::MusicVolume => format!("Music: {}", settings.music_volume),
+            SettingRow::NumberFormat => format!("Numbers: {}", settings.number_format.name()),
+            SettingRow::CrankDirection => format!("Crank: {}", settings.crank_direction.name()),
+            SettingRow::RefreshRate => format!("Frame rate: {}", settings.refresh_rate.fps()),
+            SettingRow::InvertedDisplay => {
+                format!("Invert display: {}", on_off(settings.inverted_display))
+            }
+            SettingRow::ShowTips => format!("Show tips: {}", on_off(settings.show_tips)),
+        }
+    }
+
+    fn change(&self, settings: &mut Settings, change: i32) {
+        let step_volume =
+            |volume: u8| (volume as i32 + change).clamp(0, Settings::MAX_VOLUME as i32) as u8;
+        match self {
+            SettingRow::MasterVolume => {
+                settings.master_volume = step_volume(settings.master_volume)
+            }
+            SettingRow::SfxVolume => settings.sfx_volume = step_volume(settings.sfx_volume),
+            SettingRow::MusicVolume => settings.music_volume = step_volume(settings.music_volume),
+            SettingRow::NumberFormat => {
+                settings.number_format = cycle(&NumberFormat::ALL, settings.number_format, change)
+            }
+            SettingRow::CrankDirection => {
+                settings.crank_direction =
+                    cycle(&CrankDirection::ALL, settings.crank_direction, change)
+            }
+            SettingRow::RefreshRate => {
+                settings.refresh_rate = cycle(&RefreshRate::ALL, settings.refresh_rate, change)
+            }
+            SettingRow::InvertedDisplay => settings.inverted_display = !settings.inverted_display,
+            SettingRow::ShowTips => settings.show_tips = !settings.show_tips,
+        }
+    }
+}
+
+/// Full screen panel opened from the system menu. The game is paused while it's open
+#[derive(Debug)]
+pub struct SettingsScreen {
+    settings: Settings,
+    _sprites: SpriteOwner,
+    background: Sprite,
+    rows: Vec<TextSpriteWithValue<String>>,
+    selected: usize,
+    visible: bool,
+    /// Set when the settings have changed and the game hasn't picked them up yet
+    changed: bool,
+}
+
+impl SettingsScreen {
+    const Z_INDEX: i16 = 90;
+    const ROW_Y_START: f32 = 40.0;
+    const ROW_SPACING: f32 = 24.0;
+
+    pub fn new(settings: Settings) -> Self {
+        let mut sprites = SpriteOwner::new();
+        let mut background = sprites.load_sprite_at("res/settings_background", 200.0, 120.0);
+        background.set_z_index(Self::Z_INDEX).unwrap();
+        background.set_visible(false).unwrap();
+        let rows = SettingRow::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                let mut text =
+                    sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+                let sprite = text.get_sprite_mut();
+                sprite
+                    .move_to(200.0, Self::ROW_Y_START + i as f32 * Self::ROW_SPACING)
+                    .unwrap();
+                sprite.set_z_index(Self::Z_INDEX + 1).unwrap();
+                sprite.set_visible(false).unwrap();
+                TextSpriteWithValue::new(text, String::new(), Box::new(String::clone))
+            })
+            .collect();
+        Self {
+            settings,
+            _sprites: sprites,
+            background,
+            rows,
+            selected: 0,
+            visible: false,
+            changed: false,
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.background.set_visible(visible).unwrap();
+        for row in self.rows.iter_mut() {
+            row.sprite.get_sprite_mut().set_visible(visible).unwrap();
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.selected = 0;
+        self.set_visible(true);
+        self.update_text();
+    }
+
+    /// Returns the settings if they've changed since this was last called
+    pub fn take_changed(&mut self) -> Option<&Settings> {
+        if self.changed {
+            self.changed = false;
+            Some(&self.settings)
+        } else {
+            None
+        }
+    }
+
+    fn update_text(&mut self) {
+        for (i, (row, text)) in SettingRow::ALL.iter().zip(self.rows.iter_mut()).enumerate() {
+            let marker = if i == self.selected { "> " } else { "" };
+            text.update_value(&format!("{}{}", marker, row.text(&self.settings)));
+        }
+    }
+
+    pub fn update(&mut self) {
+        if !self.visible {
+            return;
+        }
+        let (_, pressed, _) = System::get().get_button_state().unwrap();
+        let is_pressed = |button: PDButtons| (pressed & button).0 != 0;
+        if is_pressed(PDButtons::kButtonUp) {
+            self.selected = self.selected.saturating_sub(1);
+        } else if is_pressed(PDButtons::kButtonDown) {
+            self.selected = (self.selected + 1).min(self.rows.len() - 1);
+        }
+        let change = if is_pressed(PDButtons::kButtonLeft) {
+            -1
+        } else if is_pressed(PDButtons::kButtonRight) || is_pressed(PDButtons::kButtonA) {
+            1
+        } else {
+            0
+        };
+        if change != 0 {
+            SettingRow::ALL[self.selected].change(&mut self.settings, change);
+            if let Err(e) = self.settings.apply() {
+                log_to_console!("Failed to apply settings. Error: {:?}", e);
+            }
+            self.changed = true;
+        }
+        if is_pressed(PDButtons::kButtonB) {
+            if let Err(e) = self.settings.save() {
+                log_to_console!("Failed to save settings. Error: {:?}", e);
+            }
+            self.set_visible(false);
+            return;
+        }
+        self.update_text();
+    }
+}
+
+/// Shows the settings screen over the kitchen. The screen itself is shared with the kitchen, which
+/// picks up any changes once this is popped
+#[derive(Debug)]
+pub struct SettingsScene {
+    screen: Rc<RefCell<SettingsScreen>>,
+    /// Kept playing, and at the chosen volume, while the settings are open
+    music: Rc<RefCell<Music>>,
+}
+
+impl SettingsScene {
+    pub fn new(screen: Rc<RefCell<SettingsScreen>>, music: Rc<RefCell<Music>>) -> Self {
+        Self { screen, music }
+    }
+}
+
+impl Scene for SettingsScene {
+    fn enter(&mut self) {
+        self.screen.borrow_mut().show();
+    }
+
+    fn update(&mut self) -> Result<SceneTransition, Error> {
+        let mut screen = self.screen.borrow_mut();
+        screen.update();
+        let mut music = self.music.borrow_mut();
+        music.set_volume(screen.settings().music_volume());
+        music.update(false);
+        if screen.is_visible() {
+            Ok(SceneTransition::None)
+        } else {
+            Ok(SceneTransition::Pop)
+        }
+    }
+}
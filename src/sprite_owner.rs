@@ -0,0 +1,65 @@
+use crate::helpers;
+use alloc::vec::Vec;
+use crankstart::graphics::LCDColor;
+use crankstart::log_to_console;
+use crankstart::sprite::{Sprite, SpriteManager, TextSprite};
+
+/// The sprites one component has added to the display list. Dropping a [Sprite] handle doesn't
+/// take it off the display list, so each component keeps its sprites here and they're all removed
+/// when the component is dropped. This is what lets whole screens be torn down without leaving
+/// sprites behind
+///
+/// Components only hold it for its Drop and never read it, so the field is named `_sprites`
+#[derive(Debug, Default)]
+pub struct SpriteOwner {
+    sprites: Vec<Sprite>,
+}
+
+impl SpriteOwner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a new, empty sprite that's already on the display list
+    pub fn new_sprite(&mut self) -> Sprite {
+        let sprite_manager = SpriteManager::get_mut();
+        let sprite = sprite_manager.new_sprite().unwrap();
+        sprite_manager.add_sprite(&sprite).unwrap();
+        self.track(&sprite);
+        sprite
+    }
+
+    /// See [helpers::load_sprite_at]
+    pub fn load_sprite_at(&mut self, filename: &str, x: f32, y: f32) -> Sprite {
+        let sprite = helpers::load_sprite_at(filename, x, y);
+        self.track(&sprite);
+        sprite
+    }
+
+    pub fn new_text_sprite(&mut self, text: &str, color: LCDColor) -> TextSprite {
+        let text_sprite = TextSprite::new(text, color).unwrap();
+        self.track(text_sprite.get_sprite());
+        text_sprite
+    }
+
+    /// Takes ownership of a sprite that was added to the display list elsewhere
+    pub fn track(&mut self, sprite: &Sprite) {
+        self.sprites.push(sprite.clone());
+    }
+
+    /// Takes every sprite off the display list
+    pub fn remove_all(&mut self) {
+        let sprite_manager = SpriteManager::get_mut();
+        for sprite in self.sprites.drain(..) {
+            if let Err(e) = sprite_manager.remove_sprite(&sprite) {
+                log_to_console!("Failed to remove sprite: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Drop for SpriteOwner {
+    fn drop(&mut self) {
+        self.remove_all();
+    }
+}
@@ -0,0 +1,79 @@
+use alloc::vec::Vec;
+use anyhow::Error;
+use crankstart::log_to_console;
+use crankstart::sprite::Sprite;
+
+/// A component's per-frame update, given the scene that owns it
+pub type UpdateFn<S> = fn(&mut S);
+/// Custom drawing for a sprite that set "use_custom_draw"
+pub type DrawFn<S> = fn(&S) -> Result<(), Error>;
+
+#[derive(Debug)]
+struct SpriteCallbacks<S> {
+    update: Option<UpdateFn<S>>,
+    draw: Option<DrawFn<S>>,
+}
+
+/// Callbacks for the sprites in a scene, registered by the components that own them. Each
+/// registered sprite is given its own tag to look it up by, and sprites that were never registered
+/// keep the default tag of 0 and are skipped
+#[derive(Debug)]
+pub struct SpriteRegistry<S> {
+    /// Indexed by tag - 1
+    callbacks: Vec<SpriteCallbacks<S>>,
+}
+
+impl<S> SpriteRegistry<S> {
+    pub fn new() -> Self {
+        Self {
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// The sprite's callbacks, tagging it first if it hasn't been registered yet. Returns None if
+    /// every tag is already in use
+    fn callbacks_for(&mut self, sprite: &mut Sprite) -> Option<&mut SpriteCallbacks<S>> {
+        let tag = sprite.get_tag().unwrap_or(0) as usize;
+        if tag == 0 || tag > self.callbacks.len() {
+            let new_tag = match u8::try_from(self.callbacks.len() + 1) {
+                Ok(new_tag) => new_tag,
+                Err(_) => {
+                    log_to_console!("Out of sprite tags, sprite won't get callbacks");
+                    return None;
+                }
+            };
+            sprite.set_tag(new_tag).unwrap();
+            self.callbacks.push(SpriteCallbacks {
+                update: None,
+                draw: None,
+            });
+            return self.callbacks.last_mut();
+        }
+        self.callbacks.get_mut(tag - 1)
+    }
+
+    fn registered(&self, sprite: &Sprite) -> Option<&SpriteCallbacks<S>> {
+        let tag = sprite.get_tag().ok()? as usize;
+        self.callbacks.get(tag.checked_sub(1)?)
+    }
+
+    pub fn on_update(&mut self, sprite: &mut Sprite, update: UpdateFn<S>) {
+        if let Some(callbacks) = self.callbacks_for(sprite) {
+            callbacks.update = Some(update);
+        }
+    }
+
+    pub fn on_draw(&mut self, sprite: &mut Sprite, draw: DrawFn<S>) {
+        if let Some(callbacks) = self.callbacks_for(sprite) {
+            callbacks.draw = Some(draw);
+        }
+    }
+
+    pub fn update_fn(&self, sprite: &Sprite) -> Option<UpdateFn<S>> {
+        self.registered(sprite)?.update
+    }
+
+    pub fn draw_fn(&self, sprite: &Sprite) -> Option<DrawFn<S>> {
+        self.registered(sprite)?.draw
+    }
+}
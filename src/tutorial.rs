@@ -0,0 +1,330 @@
+use crate::game_events::{GameEvent, GameEventSubscriber};
+use crate::sprite_owner::SpriteOwner;
+use crate::tween::{Easing, Tween};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use crankstart::graphics::{Bitmap, Graphics, LCDColor};
+use crankstart::log_to_console;
+use crankstart::sprite::{Sprite, TextSprite};
+use crankstart::system::System;
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor, PDButtons, PDRect};
+use serde::{Deserialize, Serialize};
+
+/// The thing a step wants the player to use, which the arrow is put next to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    AButton,
+    FillBar,
+    Crank,
+    OrderBoard,
+    MenuIndicator,
+    SelectedShopItem,
+}
+
+/// Which way the highlight arrow points at its target
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pointing {
+    Down,
+    Left,
+    Right,
+}
+
+/// Where the tutorial has got to. Each step is finished by doing the thing it asks for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TutorialStep {
+    Knead,
+    FillBar,
+    Crank,
+    Sell,
+    OpenShop,
+    BuyUpgrade,
+    Done,
+}
+
+/// Saves from before the tutorial existed belong to players who already know the game
+impl Default for TutorialStep {
+    fn default() -> Self {
+        TutorialStep::Done
+    }
+}
+
+impl TutorialStep {
+    fn next(self) -> Self {
+        match self {
+            TutorialStep::Knead => TutorialStep::FillBar,
+            TutorialStep::FillBar => TutorialStep::Crank,
+            TutorialStep::Crank => TutorialStep::Sell,
+            TutorialStep::Sell => TutorialStep::OpenShop,
+            TutorialStep::OpenShop => TutorialStep::BuyUpgrade,
+            TutorialStep::BuyUpgrade | TutorialStep::Done => TutorialStep::Done,
+        }
+    }
+
+    fn is_finished_by(self, event: &GameEvent) -> bool {
+        match self {
+            TutorialStep::Knead => matches!(event, GameEvent::Kneaded),
+            TutorialStep::FillBar => matches!(event, GameEvent::DoughCreated),
+            TutorialStep::Crank => matches!(event, GameEvent::SheetMade { .. }),
+            TutorialStep::Sell => matches!(event, GameEvent::MoneyGained { .. }),
+            TutorialStep::OpenShop => matches!(event, GameEvent::ShopOpened),
+            TutorialStep::BuyUpgrade => matches!(event, GameEvent::UpgradeBought { .. }),
+            TutorialStep::Done => false,
+        }
+    }
+
+    fn callout(self) -> &'static str {
+        match self {
+            TutorialStep::Knead => "Press A to knead the dough",
+            TutorialStep::FillBar => "Keep kneading to fill the bar",
+            TutorialStep::Crank => "Turn the crank to roll a sheet",
+            TutorialStep::Sell => "Press Right, then A to sell",
+            TutorialStep::OpenShop => "Press Left to open the shop",
+            TutorialStep::BuyUpgrade => "Press A to buy an upgrade",
+            TutorialStep::Done => "",
+        }
+    }
+
+    /// What the arrow is on for this step and which way it points at it
+    fn highlight(self) -> Option<(Target, Pointing)> {
+        match self {
+            TutorialStep::Knead => Some((Target::AButton, Pointing::Down)),
+            TutorialStep::FillBar => Some((Target::FillBar, Pointing::Down)),
+            TutorialStep::Crank => Some((Target::Crank, Pointing::Down)),
+            // The order board waits off the right edge, so the arrow points at where it comes in
+            TutorialStep::Sell => Some((Target::OrderBoard, Pointing::Right)),
+            TutorialStep::OpenShop => Some((Target::MenuIndicator, Pointing::Down)),
+            TutorialStep::BuyUpgrade => Some((Target::SelectedShopItem, Pointing::Left)),
+            TutorialStep::Done => None,
+        }
+    }
+}
+
+/// Walks a new player through making and selling their first pasta, with an arrow on whatever
+/// they should use next and a callout saying what to do with it
+#[derive(Debug)]
+pub struct Tutorial {
+    step: TutorialStep,
+    /// The step the sprites were last set up for
+    shown_step: Option<TutorialStep>,
+    _sprites: SpriteOwner,
+    arrow: Sprite,
+    arrow_down: Bitmap,
+    arrow_side: Bitmap,
+    callout: Sprite,
+    /// Dithered steps of the callout from faintest to fully drawn, used to fade it in and out
+    callout_images: Vec<Bitmap>,
+    /// From 0.0 hidden to 1.0 fully shown
+    fade: Tween,
+    /// Index into `callout_images` plus one that's on screen, 0 when hidden
+    shown_fade_step: usize,
+    callout_text: TextSprite,
+    skip_text: TextSprite,
+    /// Elapsed time B has been held since, for skipping
+    skip_held_since: Option<f32>,
+    /// Set from the system menu, as its callbacks can't borrow the game state
+    restart_requested: Rc<Cell<bool>>,
+}
+
+impl Tutorial {
+    const Z_INDEX: i16 = 40;
+    const CALLOUT_POS: (f32, f32) = (200.0, 168.0);
+    const SKIP_HOLD_SECS: f32 = 1.5;
+    /// How far the arrow bobs towards its target
+    const BOB_PX: f32 = 4.0;
+    /// Space between the arrow and its target
+    const ARROW_GAP: f32 = 2.0;
+    const FADE_SECS: f32 = 0.3;
+
+    pub fn new(step: TutorialStep) -> Self {
+        let mut sprites = SpriteOwner::new();
+        let graphics = Graphics::get();
+        let arrow_down = graphics.load_bitmap("res/tutorial_arrow_down").unwrap();
+        let arrow_side = graphics.load_bitmap("res/tutorial_arrow_right").unwrap();
+        let mut arrow = sprites.new_sprite();
+        arrow
+            .set_image(arrow_down.clone(), LCDBitmapFlip::kBitmapUnflipped)
+            .unwrap();
+        arrow.set_z_index(Self::Z_INDEX).unwrap();
+        let (x, y) = Self::CALLOUT_POS;
+        let mut callout = sprites.load_sprite_at("res/callout", x, y);
+        callout.set_z_index(Self::Z_INDEX).unwrap();
+        callout.set_visible(false).unwrap();
+        let mut callout_images: Vec<Bitmap> = (1..=3)
+            .map(|step| {
+                graphics
+                    .load_bitmap(&format!("res/callout_fade{}", step))
+                    .unwrap()
+            })
+            .collect();
+        callout_images.push(graphics.load_bitmap("res/callout").unwrap());
+        let mut callout_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        callout_text.get_sprite_mut().move_to(x, y - 9.0).unwrap();
+        callout_text
+            .get_sprite_mut()
+            .set_z_index(Self::Z_INDEX + 1)
+            .unwrap();
+        let mut skip_text = sprites.new_text_sprite(
+            "Hold B to skip the tutorial",
+            LCDColor::Solid(LCDSolidColor::kColorBlack),
+        );
+        skip_text.get_sprite_mut().move_to(x, y + 10.0).unwrap();
+        skip_text
+            .get_sprite_mut()
+            .set_z_index(Self::Z_INDEX + 1)
+            .unwrap();
+        let mut s = Self {
+            step,
+            shown_step: None,
+            _sprites: sprites,
+            arrow,
+            arrow_down,
+            arrow_side,
+            callout,
+            callout_images,
+            fade: Tween::settled(0.0, Self::FADE_SECS, Easing::Linear),
+            shown_fade_step: 0,
+            callout_text,
+            skip_text,
+            skip_held_since: None,
+            restart_requested: Rc::new(Cell::new(false)),
+        };
+        s.set_visible(false);
+        s.update_visuals(None);
+        s
+    }
+
+    pub fn to_saved(&self) -> TutorialStep {
+        self.step
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.step != TutorialStep::Done
+    }
+
+    /// The thing the arrow should be next to, whose bounds are passed into [Tutorial::update]
+    pub fn target(&self) -> Option<Target> {
+        self.step.highlight().map(|(target, _)| target)
+    }
+
+    /// Handle for the system menu to start the tutorial over
+    pub fn restart_request(&self) -> Rc<Cell<bool>> {
+        self.restart_requested.clone()
+    }
+
+    pub fn skip(&mut self) {
+        if self.is_running() {
+            log_to_console!("Skipping tutorial at {:?}", self.step);
+            self.step = TutorialStep::Done;
+        }
+    }
+
+    /// Shows or hides the arrow and the text, which only appear once the callout is fully faded in
+    fn set_visible(&mut self, visible: bool) {
+        self.arrow.set_visible(visible).unwrap();
+        self.callout_text
+            .get_sprite_mut()
+            .set_visible(visible)
+            .unwrap();
+        self.skip_text
+            .get_sprite_mut()
+            .set_visible(visible)
+            .unwrap();
+    }
+
+    fn update_fade(&mut self) {
+        let steps = self.callout_images.len();
+        let step = ((self.fade.value() * steps as f32 + 0.5) as usize).min(steps);
+        if step == self.shown_fade_step {
+            return;
+        }
+        self.shown_fade_step = step;
+        if step == 0 {
+            self.callout.set_visible(false).unwrap();
+        } else {
+            self.callout
+                .set_image(
+                    self.callout_images[step - 1].clone(),
+                    LCDBitmapFlip::kBitmapUnflipped,
+                )
+                .unwrap();
+            self.callout.set_visible(true).unwrap();
+        }
+        self.set_visible(step == steps);
+    }
+
+    fn update_visuals(&mut self, target_bounds: Option<PDRect>) {
+        if self.shown_step != Some(self.step) {
+            self.shown_step = Some(self.step);
+            self.fade
+                .retarget(if self.is_running() { 1.0 } else { 0.0 });
+            self.callout_text.update_text(self.step.callout()).unwrap();
+            if let Some((_, pointing)) = self.step.highlight() {
+                let (image, flip) = match pointing {
+                    Pointing::Down => (&self.arrow_down, LCDBitmapFlip::kBitmapUnflipped),
+                    Pointing::Right => (&self.arrow_side, LCDBitmapFlip::kBitmapUnflipped),
+                    Pointing::Left => (&self.arrow_side, LCDBitmapFlip::kBitmapFlippedX),
+                };
+                self.arrow.set_image(image.clone(), flip).unwrap();
+            }
+        }
+        self.update_fade();
+        if let (Some((_, pointing)), Some(target)) = (self.step.highlight(), target_bounds) {
+            // Bob towards the target and back once a second
+            let now = System::get().get_elapsed_time().unwrap_or(0.0);
+            let t = now % 1.0;
+            let bob = Self::BOB_PX * 2.0 * t.min(1.0 - t);
+            // Sit just outside the target, on the side the arrow points from
+            let arrow = self.arrow.get_bounds().unwrap();
+            let centre_x = target.x + target.width / 2.0;
+            let centre_y = target.y + target.height / 2.0;
+            let (x, y) = match pointing {
+                Pointing::Down => {
+                    let y = target.y - Self::ARROW_GAP - arrow.height / 2.0;
+                    (centre_x, y.max(arrow.height / 2.0) + bob)
+                }
+                Pointing::Left => {
+                    let x = target.x + target.width + Self::ARROW_GAP + arrow.width / 2.0;
+                    (x - bob, centre_y)
+                }
+                Pointing::Right => {
+                    let x = target.x - Self::ARROW_GAP - arrow.width / 2.0;
+                    (x + bob, centre_y)
+                }
+            };
+            self.arrow.move_to(x, y).unwrap();
+        }
+    }
+
+    /// `target_bounds` are the bounds of the sprite for [Tutorial::target], if there is one
+    pub fn update(&mut self, target_bounds: Option<PDRect>) {
+        if self.restart_requested.replace(false) {
+            self.step = TutorialStep::Knead;
+        }
+        if self.is_running() {
+            let (held, _, _) = System::get().get_button_state().unwrap();
+            if (held & PDButtons::kButtonB).0 != 0 {
+                let now = System::get().get_elapsed_time().unwrap_or(0.0);
+                let since = *self.skip_held_since.get_or_insert(now);
+                if now - since >= Self::SKIP_HOLD_SECS {
+                    self.skip();
+                }
+            } else {
+                self.skip_held_since = None;
+            }
+        }
+        self.update_visuals(target_bounds);
+    }
+}
+
+impl GameEventSubscriber for Tutorial {
+    fn on_events(&mut self, events: &[GameEvent]) {
+        for event in events {
+            if self.step.is_finished_by(event) {
+                self.step = self.step.next();
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::game_value::{GameDecimal, GameUInt};
+use crankstart::system::System;
+
+/// Shapes how a [Tween] moves between its start and end
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// Starts slow and speeds up
+    EaseIn,
+    /// Starts fast and slows into place
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps progress from 0.0 to 1.0 onto the curve
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+                }
+            }
+        }
+    }
+}
+
+fn now() -> f32 {
+    System::get().get_elapsed_time().unwrap_or(0.0)
+}
+
+/// A value animated between two points over time. It's driven by elapsed time, so it runs at the
+/// same speed whatever the frame rate and only needs reading, not updating
+#[derive(Debug, Clone)]
+pub struct Tween {
+    from: f32,
+    to: f32,
+    started: f32,
+    duration: f32,
+    easing: Easing,
+}
+
+impl Tween {
+    pub fn new(from: f32, to: f32, duration: f32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            started: now(),
+            duration,
+            easing,
+        }
+    }
+
+    /// A tween that has already finished at `value`
+    pub fn settled(value: f32, duration: f32, easing: Easing) -> Self {
+        Self {
+            from: value,
+            to: value,
+            started: now() - duration,
+            duration,
+            easing,
+        }
+    }
+
+    /// Heads towards `to` from wherever the tween is now, so changing direction midway is smooth
+    pub fn retarget(&mut self, to: f32) {
+        if to == self.to {
+            return;
+        }
+        self.from = self.value();
+        self.to = to;
+        self.started = now();
+    }
+
+    /// Progress from 0.0 to 1.0, before easing
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        ((now() - self.started) / self.duration).clamp(0.0, 1.0)
+    }
+
+    pub fn value(&self) -> f32 {
+        self.from + (self.to - self.from) * self.easing.apply(self.progress())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
+
+/// A number that rolls up to its new value rather than jumping, e.g. the money counter. Drops are
+/// shown straight away, as rolling down after spending reads as the money draining away
+#[derive(Debug, Clone)]
+pub struct RollingCounter {
+    from: GameUInt,
+    to: GameUInt,
+    tween: Tween,
+}
+
+impl RollingCounter {
+    /// Steps the rolling value is split into
+    const RESOLUTION: usize = 1000;
+
+    pub fn new(value: GameUInt, duration: f32) -> Self {
+        Self {
+            from: value.clone(),
+            to: value,
+            tween: Tween::settled(1.0, duration, Easing::EaseOut),
+        }
+    }
+
+    pub fn set_target(&mut self, value: &GameUInt) {
+        if *value == self.to {
+            return;
+        }
+        let current = self.value();
+        if *value < current {
+            self.from = value.clone();
+        } else {
+            self.from = current;
+        }
+        self.to = value.clone();
+        self.tween = Tween::new(0.0, 1.0, self.tween.duration, Easing::EaseOut);
+    }
+
+    pub fn value(&self) -> GameUInt {
+        if self.tween.is_finished() {
+            return self.to.clone();
+        }
+        let diff = match self.to.checked_sub(&self.from) {
+            Some(diff) => diff,
+            None => return self.to.clone(),
+        };
+        let steps = (self.tween.value() * Self::RESOLUTION as f32) as usize;
+        let fraction = GameDecimal::from_ratio(steps, Self::RESOLUTION);
+        self.from.clone() + diff.mul_decimal(&fraction)
+    }
+}
@@ -0,0 +1,322 @@
+use crate::core_elements::{CoreParameters, TextSpriteWithValue};
+use crate::sprite_owner::SpriteOwner;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use crankstart::graphics::{Bitmap, Graphics, LCDColor};
+use crankstart::sprite::Sprite;
+use crankstart_sys::{LCDBitmapFlip, LCDSolidColor};
+
+/// One step of the work animation: which way the worker faces and how far they lean in
+const WORK_FRAMES: [(LCDBitmapFlip, f32); 4] = [
+    (LCDBitmapFlip::kBitmapUnflipped, 0.0),
+    (LCDBitmapFlip::kBitmapUnflipped, 2.0),
+    (LCDBitmapFlip::kBitmapFlippedX, 0.0),
+    (LCDBitmapFlip::kBitmapFlippedX, 2.0),
+];
+
+/// The different kinds of staff that can be hired from the shop
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WorkerKind {
+    /// The original kneader
+    Gran,
+    /// Kneads faster than a gran
+    Nonna,
+    /// Cheap, but slow to knead
+    Apprentice,
+    /// Doesn't knead, turns the crank instead
+    CrankingCousin,
+    /// Doesn't knead, sells each sheet for a bonus
+    DeliveryBoy,
+}
+
+impl WorkerKind {
+    pub const ALL: [WorkerKind; 5] = [
+        WorkerKind::Gran,
+        WorkerKind::Nonna,
+        WorkerKind::Apprentice,
+        WorkerKind::CrankingCousin,
+        WorkerKind::DeliveryBoy,
+    ];
+
+    fn image_path(&self) -> &'static str {
+        match self {
+            WorkerKind::Gran => "res/gran",
+            WorkerKind::Nonna => "res/nonna",
+            WorkerKind::Apprentice => "res/apprentice",
+            WorkerKind::CrankingCousin => "res/cousin",
+            WorkerKind::DeliveryBoy => "res/delivery_boy",
+        }
+    }
+
+    /// Seconds for one full work cycle
+    fn cycle_duration(&self) -> f32 {
+        match self {
+            WorkerKind::Gran => 1.0,
+            WorkerKind::Nonna => 0.6,
+            WorkerKind::Apprentice => 1.5,
+            WorkerKind::CrankingCousin | WorkerKind::DeliveryBoy => 1.2,
+        }
+    }
+
+    /// How much each completed work cycle fills the knead bar
+    fn knead_per_cycle(&self) -> f32 {
+        match self {
+            WorkerKind::Gran => 0.02,
+            WorkerKind::Nonna => 0.03,
+            WorkerKind::Apprentice => 0.01,
+            WorkerKind::CrankingCousin | WorkerKind::DeliveryBoy => 0.0,
+        }
+    }
+
+    pub fn count(&self, parameters: &CoreParameters) -> usize {
+        match self {
+            WorkerKind::Gran => parameters.auto_knead_level,
+            WorkerKind::Nonna => parameters.nonna_level,
+            WorkerKind::Apprentice => parameters.apprentice_level,
+            WorkerKind::CrankingCousin => parameters.cousin_level,
+            WorkerKind::DeliveryBoy => parameters.delivery_boy_level,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Worker {
+    /// Holds just this worker's sprite, so letting a worker go takes them off screen
+    _sprites: SpriteOwner,
+    sprite: Sprite,
+    image: Bitmap,
+    /// Seconds into the current work cycle
+    cycle_pos: f32,
+    cycle_duration: f32,
+    frame: usize,
+    home: (f32, f32),
+}
+
+impl Worker {
+    fn new(image: &Bitmap, cycle_duration: f32, cycle_offset: f32) -> Self {
+        let mut sprites = SpriteOwner::new();
+        let mut sprite = sprites.new_sprite();
+        sprite
+            .set_image(image.clone(), LCDBitmapFlip::kBitmapUnflipped)
+            .unwrap();
+        sprite.set_visible(false).unwrap();
+        Self {
+            _sprites: sprites,
+            sprite,
+            image: image.clone(),
+            cycle_pos: cycle_offset * cycle_duration,
+            cycle_duration,
+            frame: 0,
+            home: (0.0, 0.0),
+        }
+    }
+
+    /// Advances the work cycle, returning the number of cycles completed
+    fn work(&mut self, dt: f32) -> usize {
+        self.cycle_pos += dt;
+        let mut completed = 0;
+        while self.cycle_pos >= self.cycle_duration {
+            self.cycle_pos -= self.cycle_duration;
+            completed += 1;
+        }
+        completed
+    }
+
+    /// `lead` is how many seconds the frame is past the last step
+    fn current_frame(&self, lead: f32) -> usize {
+        let frame_pct = (self.cycle_pos + lead) / self.cycle_duration;
+        (frame_pct * WORK_FRAMES.len() as f32) as usize % WORK_FRAMES.len()
+    }
+
+    fn place(&mut self, home: (f32, f32), visible: bool) {
+        self.home = home;
+        self.sprite.set_visible(visible).unwrap();
+        self.draw_frame(0.0, true);
+    }
+
+    fn draw_frame(&mut self, lead: f32, force: bool) {
+        let frame = self.current_frame(lead);
+        if frame == self.frame && !force {
+            return;
+        }
+        self.frame = frame;
+        let (flip, lean) = WORK_FRAMES[frame];
+        self.sprite.set_image(self.image.clone(), flip).unwrap();
+        self.sprite
+            .move_to(self.home.0, self.home.1 + lean)
+            .unwrap();
+    }
+}
+
+/// All hired workers of one kind
+#[derive(Debug)]
+struct Crew {
+    kind: WorkerKind,
+    image: Bitmap,
+    workers: Vec<Worker>,
+    _sprites: SpriteOwner,
+    stack_text: TextSpriteWithValue<usize>,
+}
+
+impl Crew {
+    fn new(kind: WorkerKind) -> Self {
+        let image = Graphics::get().load_bitmap(kind.image_path()).unwrap();
+        let mut sprites = SpriteOwner::new();
+        let mut stack_text =
+            sprites.new_text_sprite("", LCDColor::Solid(LCDSolidColor::kColorBlack));
+        stack_text.get_sprite_mut().set_visible(false).unwrap();
+        let stack_text =
+            TextSpriteWithValue::new(stack_text, 0, Box::new(|count| format!("x{}", count)));
+        Self {
+            kind,
+            image,
+            workers: Vec::new(),
+            _sprites: sprites,
+            stack_text,
+        }
+    }
+
+    /// Returns true if the number of workers changed
+    fn set_count(&mut self, count: usize) -> bool {
+        if count == self.workers.len() {
+            return false;
+        }
+        // Only new hires need a sprite, existing workers keep theirs and their place in the cycle.
+        // Workers let go take their sprite with them when dropped
+        while self.workers.len() < count {
+            // Spread cycle starts out so the crew don't all work in lockstep
+            let offset = (self.workers.len() as f32 * 0.37) % 1.0;
+            let cycle_duration = self.kind.cycle_duration();
+            self.workers
+                .push(Worker::new(&self.image, cycle_duration, offset));
+        }
+        self.workers.truncate(count);
+        self.stack_text.update_value(&count);
+        true
+    }
+
+    fn set_stacked(&mut self, stacked_at: Option<(f32, f32)>) {
+        let text_sprite = self.stack_text.sprite.get_sprite_mut();
+        match stacked_at {
+            Some((x, y)) if !self.workers.is_empty() => {
+                text_sprite.move_to(x + 30.0, y).unwrap();
+                text_sprite.set_visible(true).unwrap();
+            }
+            _ => text_sprite.set_visible(false).unwrap(),
+        }
+    }
+}
+
+/// Workers hired from the shop. Each one runs through their own work cycle, and kneaders add to
+/// the fill bar every time they complete one
+#[derive(Debug)]
+pub struct Workers {
+    crews: Vec<Crew>,
+    midpoint: (f32, f32),
+    /// Length of the last step, for drawing the work cycles between steps
+    last_step: f32,
+}
+
+impl Workers {
+    const MAX_COLUMNS: usize = 6;
+    const MAX_ROWS: usize = 2;
+    const COLUMN_SPACING: f32 = 20.0;
+    const ROW_SPACING: f32 = 24.0;
+    const STACK_COLUMNS: usize = 2;
+    const STACK_COLUMN_SPACING: f32 = 60.0;
+
+    pub fn new(x: f32, y: f32) -> Self {
+        let crews = WorkerKind::ALL
+            .iter()
+            .map(|kind| Crew::new(*kind))
+            .collect();
+        Self {
+            crews,
+            midpoint: (x, y),
+            last_step: 0.0,
+        }
+    }
+
+    fn max_drawn() -> usize {
+        Self::MAX_COLUMNS * Self::MAX_ROWS
+    }
+
+    /// Position of the i-th of `count` workers, laid out in centred rows below the midpoint
+    fn layout_position(&self, i: usize, count: usize) -> (f32, f32) {
+        let row = i / Self::MAX_COLUMNS;
+        let col = i % Self::MAX_COLUMNS;
+        let in_row = (count - row * Self::MAX_COLUMNS).min(Self::MAX_COLUMNS);
+        let centred_col = col as f32 - (in_row as f32 - 1.0) / 2.0;
+        (
+            self.midpoint.0 + centred_col * Self::COLUMN_SPACING,
+            self.midpoint.1 + row as f32 * Self::ROW_SPACING,
+        )
+    }
+
+    /// Position of the i-th crew when there are too many workers to draw individually, and each
+    /// crew is drawn as a single worker with a count next to them
+    fn stack_position(&self, i: usize) -> (f32, f32) {
+        let row = i / Self::STACK_COLUMNS;
+        let col = i % Self::STACK_COLUMNS;
+        let centred_col = col as f32 - (Self::STACK_COLUMNS as f32 - 1.0) / 2.0;
+        (
+            self.midpoint.0 + centred_col * Self::STACK_COLUMN_SPACING - 15.0,
+            self.midpoint.1 + row as f32 * Self::ROW_SPACING,
+        )
+    }
+
+    fn layout(&mut self) {
+        let total: usize = self.crews.iter().map(|crew| crew.workers.len()).sum();
+        let stacked = total > Self::max_drawn();
+        let mut i = 0;
+        let mut crew_slot = 0;
+        for crew_idx in 0..self.crews.len() {
+            let stack_home = self.stack_position(crew_slot);
+            for worker_idx in 0..self.crews[crew_idx].workers.len() {
+                let (home, visible) = if stacked {
+                    (stack_home, worker_idx == 0)
+                } else {
+                    (self.layout_position(i, total), true)
+                };
+                self.crews[crew_idx].workers[worker_idx].place(home, visible);
+                i += 1;
+            }
+            let crew = &mut self.crews[crew_idx];
+            crew.set_stacked(if stacked { Some(stack_home) } else { None });
+            if !crew.workers.is_empty() {
+                crew_slot += 1;
+            }
+        }
+    }
+
+    /// Runs every worker's cycle for `dt` seconds and returns how much the kneaders filled the bar
+    /// between them
+    pub fn step(&mut self, dt: f32, parameters: &CoreParameters) -> f32 {
+        let mut changed = false;
+        for crew in self.crews.iter_mut() {
+            changed |= crew.set_count(crew.kind.count(parameters));
+        }
+        if changed {
+            self.layout();
+        }
+
+        self.last_step = dt;
+        let mut knead = 0.0;
+        for crew in self.crews.iter_mut() {
+            let cycles: usize = crew.workers.iter_mut().map(|worker| worker.work(dt)).sum();
+            knead += cycles as f32 * crew.kind.knead_per_cycle();
+        }
+        knead
+    }
+
+    /// Draws the workers `alpha` of the way from the last step to the next
+    pub fn update(&mut self, alpha: f32) {
+        let lead = alpha * self.last_step;
+        for crew in self.crews.iter_mut() {
+            for worker in crew.workers.iter_mut() {
+                worker.draw_frame(lead, false);
+            }
+        }
+    }
+}